@@ -0,0 +1,360 @@
+//! Merging of two subtitle tracks into dual subtitles.
+
+use crate::{VttCue, VttTimestamp, WebVtt};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// How the two texts of a merged cue are marked up.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum BilingualLayout {
+    /// The primary text, followed by the secondary text on the next lines.
+    #[default]
+    Lines,
+    /// Each text in a `<lang>` span with the given language tags, primary first.
+    Lang {
+        /// The language tag of the primary text, e.g. `en`.
+        primary: String,
+        /// The language tag of the secondary text, e.g. `de`.
+        secondary: String,
+    },
+}
+
+/// How merged cues are timed when the cues of the two tracks don't line up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CueAlignment {
+    /// Keep the timing of the primary cues. Each secondary cue is attached to
+    /// the primary cue it overlaps most; secondary cues overlapping none are
+    /// kept as cues of their own.
+    #[default]
+    Primary,
+    /// Split cues at every boundary of either track, so each cue shows exactly
+    /// the texts active during it.
+    Split,
+    /// Combine cues of both tracks that overlap, directly or through other
+    /// cues, into a single cue spanning them all.
+    Union,
+}
+
+/// Options for `WebVtt::merge_bilingual`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BilingualConfig {
+    /// How the two texts are marked up.
+    pub layout: BilingualLayout,
+    /// How cue boundaries are reconciled.
+    pub alignment: CueAlignment,
+}
+
+/// A merged cue being built, with the cues of each track it shows.
+struct Pair {
+    start: VttTimestamp,
+    end: VttTimestamp,
+    primary: Vec<usize>,
+    secondary: Vec<usize>,
+}
+
+impl WebVtt {
+    /// Merges this track with a track in another language, aligning cues by
+    /// time overlap.
+    ///
+    /// The header and styles are taken from this track. Merged cues are sorted
+    /// by start time.
+    pub fn merge_bilingual(&self, other: &WebVtt, config: &BilingualConfig) -> WebVtt {
+        let pairs = match config.alignment {
+            CueAlignment::Primary => align_primary(self, other),
+            CueAlignment::Split => align_split(self, other),
+            CueAlignment::Union => align_union(self, other),
+        };
+
+        let mut cues: Vec<VttCue> = pairs
+            .into_iter()
+            .map(|pair| {
+                let primary = join(&self.cues, &pair.primary);
+                let secondary = join(&other.cues, &pair.secondary);
+                let payload = match config.layout {
+                    BilingualLayout::Lines => [primary, secondary]
+                        .into_iter()
+                        .filter(|text| !text.is_empty())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    BilingualLayout::Lang {
+                        primary: ref primary_lang,
+                        secondary: ref secondary_lang,
+                    } => [(primary_lang, primary), (secondary_lang, secondary)]
+                        .into_iter()
+                        .filter(|(_, text)| !text.is_empty())
+                        .map(|(lang, text)| format!("<lang {}>{}</lang>", lang, text))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+
+                // A cue keeps its identity only when it is the primary cue unchanged.
+                let original = match (config.alignment, pair.primary.as_slice()) {
+                    (CueAlignment::Primary, &[i]) => Some(&self.cues[i]),
+                    _ => None,
+                };
+                VttCue {
+                    identifier: original.and_then(|cue| cue.identifier.clone()),
+                    start: pair.start,
+                    end: pair.end,
+                    settings: original.and_then(|cue| cue.settings.clone()),
+                    payload,
+                }
+            })
+            .collect();
+        cues.sort_by_key(|cue| (cue.start, cue.end));
+
+        WebVtt {
+            header: self.header.clone(),
+            styles: self.styles.clone(),
+            cues,
+        }
+    }
+}
+
+/// Joins the trimmed payloads of the given cues, one per line.
+fn join(cues: &[VttCue], indices: &[usize]) -> String {
+    indices
+        .iter()
+        .map(|&i| cues[i].payload.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn overlap(a: &VttCue, b: &VttCue) -> Duration {
+    let start = a.start.max(b.start).as_duration();
+    let end = a.end.min(b.end).as_duration();
+    end.saturating_sub(start)
+}
+
+fn align_primary(primary: &WebVtt, secondary: &WebVtt) -> Vec<Pair> {
+    let index = primary.index();
+    let mut pairs: Vec<Pair> = primary
+        .cues
+        .iter()
+        .enumerate()
+        .map(|(i, cue)| Pair {
+            start: cue.start,
+            end: cue.end,
+            primary: vec![i],
+            secondary: Vec::new(),
+        })
+        .collect();
+
+    for (j, cue) in secondary.cues.iter().enumerate() {
+        let best = index
+            .overlapping(&cue.start, &cue.end)
+            .into_iter()
+            .max_by_key(|&i| (overlap(&primary.cues[i], cue), core::cmp::Reverse(i)));
+        match best {
+            Some(i) => pairs[i].secondary.push(j),
+            None => pairs.push(Pair {
+                start: cue.start,
+                end: cue.end,
+                primary: Vec::new(),
+                secondary: vec![j],
+            }),
+        }
+    }
+    pairs
+}
+
+fn align_split(primary: &WebVtt, secondary: &WebVtt) -> Vec<Pair> {
+    let mut bounds: Vec<VttTimestamp> = primary
+        .cues
+        .iter()
+        .chain(&secondary.cues)
+        .flat_map(|cue| [cue.start, cue.end])
+        .collect();
+    bounds.sort();
+    bounds.dedup();
+
+    let (primary_index, secondary_index) = (primary.index(), secondary.index());
+    let mut pairs: Vec<Pair> = Vec::new();
+    for window in bounds.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        let active_primary = primary_index.active_at(&start);
+        let active_secondary = secondary_index.active_at(&start);
+        if active_primary.is_empty() && active_secondary.is_empty() {
+            continue;
+        }
+        match pairs.last_mut() {
+            Some(last)
+                if last.end == start
+                    && last.primary == active_primary
+                    && last.secondary == active_secondary =>
+            {
+                last.end = end
+            }
+            _ => pairs.push(Pair {
+                start,
+                end,
+                primary: active_primary,
+                secondary: active_secondary,
+            }),
+        }
+    }
+    pairs
+}
+
+fn align_union(primary: &WebVtt, secondary: &WebVtt) -> Vec<Pair> {
+    // Sweep over the cues of both tracks by start time, grouping overlapping ones.
+    let mut cues: Vec<(bool, usize, &VttCue)> = primary
+        .cues
+        .iter()
+        .enumerate()
+        .map(|(i, cue)| (true, i, cue))
+        .chain(
+            secondary
+                .cues
+                .iter()
+                .enumerate()
+                .map(|(i, cue)| (false, i, cue)),
+        )
+        .collect();
+    cues.sort_by_key(|&(is_primary, i, cue)| (cue.start, !is_primary, i));
+
+    let mut pairs: Vec<Pair> = Vec::new();
+    for (is_primary, i, cue) in cues {
+        match pairs.last_mut() {
+            Some(last) if cue.start < last.end => last.end = last.end.max(cue.end),
+            _ => pairs.push(Pair {
+                start: cue.start,
+                end: cue.end,
+                primary: Vec::new(),
+                secondary: Vec::new(),
+            }),
+        }
+        if let Some(pair) = pairs.last_mut() {
+            if is_primary {
+                pair.primary.push(i);
+            } else {
+                pair.secondary.push(i);
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use core::str::FromStr;
+
+    fn tracks() -> (WebVtt, WebVtt) {
+        let english = WebVtt::from_str(
+            "WEBVTT
+
+intro
+00:00:01.000 --> 00:00:04.000
+Good morning.
+
+00:00:04.000 --> 00:00:06.000
+How are you?",
+        )
+        .unwrap();
+        let german = WebVtt::from_str(
+            "WEBVTT
+
+00:00:01.500 --> 00:00:03.000
+Guten Morgen.
+
+00:00:03.000 --> 00:00:05.500
+Wie geht es dir?
+
+00:00:07.000 --> 00:00:08.000
+Danke.",
+        )
+        .unwrap();
+        (english, german)
+    }
+
+    fn timings(vtt: &WebVtt) -> Vec<(u128, u128, &str)> {
+        vtt.cues
+            .iter()
+            .map(|c| {
+                (
+                    c.start.as_duration().as_millis(),
+                    c.end.as_duration().as_millis(),
+                    c.payload.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_primary_timing() {
+        let (english, german) = tracks();
+        let merged = english.merge_bilingual(&german, &BilingualConfig::default());
+        assert_eq!(
+            timings(&merged),
+            vec![
+                (1000, 4000, "Good morning.\nGuten Morgen."),
+                (4000, 6000, "How are you?\nWie geht es dir?"),
+                (7000, 8000, "Danke."),
+            ]
+        );
+        assert_eq!(merged.cues[0].identifier.as_deref(), Some("intro"));
+    }
+
+    #[test]
+    fn test_merge_split_with_lang_spans() {
+        let (english, german) = tracks();
+        let config = BilingualConfig {
+            layout: BilingualLayout::Lang {
+                primary: "en".to_string(),
+                secondary: "de".to_string(),
+            },
+            alignment: CueAlignment::Split,
+        };
+        let merged = english.merge_bilingual(&german, &config);
+        assert_eq!(
+            timings(&merged),
+            vec![
+                (1000, 1500, "<lang en>Good morning.</lang>"),
+                (
+                    1500,
+                    3000,
+                    "<lang en>Good morning.</lang>\n<lang de>Guten Morgen.</lang>"
+                ),
+                (
+                    3000,
+                    4000,
+                    "<lang en>Good morning.</lang>\n<lang de>Wie geht es dir?</lang>"
+                ),
+                (
+                    4000,
+                    5500,
+                    "<lang en>How are you?</lang>\n<lang de>Wie geht es dir?</lang>"
+                ),
+                (5500, 6000, "<lang en>How are you?</lang>"),
+                (7000, 8000, "<lang de>Danke.</lang>"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_union() {
+        let (english, german) = tracks();
+        let config = BilingualConfig {
+            alignment: CueAlignment::Union,
+            ..BilingualConfig::default()
+        };
+        let merged = english.merge_bilingual(&german, &config);
+        assert_eq!(
+            timings(&merged),
+            vec![
+                (
+                    1000,
+                    6000,
+                    "Good morning.\nHow are you?\nGuten Morgen.\nWie geht es dir?"
+                ),
+                (7000, 8000, "Danke."),
+            ]
+        );
+    }
+}
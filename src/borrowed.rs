@@ -0,0 +1,337 @@
+//! A borrowed document model, parsed without copying the source text.
+
+use crate::line_ending::{lines, strip_bom};
+use crate::{is_block_keyword, parse_settings, VttCue, VttHeader, VttParseError, VttSettings};
+use crate::{VttTimestamp, WebVtt};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+/// A cue borrowing its text from the parsed source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VttCueRef<'a> {
+    /// An optional identifier for the cue.
+    pub identifier: Option<Cow<'a, str>>,
+    /// The start time of the cue.
+    pub start: VttTimestamp,
+    /// The end time of the cue.
+    pub end: VttTimestamp,
+    /// Optional settings for the cue.
+    pub settings: Option<VttSettings>,
+    /// The text of the cue. Borrowed unless the source uses `\r\n` line
+    /// endings and the payload spans several lines.
+    pub payload: Cow<'a, str>,
+}
+
+impl VttCueRef<'_> {
+    /// Converts the cue into an owned `VttCue`.
+    pub fn into_owned(self) -> VttCue {
+        VttCue {
+            identifier: self.identifier.map(Cow::into_owned),
+            start: self.start,
+            end: self.end,
+            settings: self.settings,
+            payload: self.payload.into_owned(),
+        }
+    }
+}
+
+impl<'a> From<&'a VttCue> for VttCueRef<'a> {
+    fn from(cue: &'a VttCue) -> Self {
+        VttCueRef {
+            identifier: cue.identifier.as_deref().map(Cow::Borrowed),
+            start: cue.start,
+            end: cue.end,
+            settings: cue.settings.clone(),
+            payload: Cow::Borrowed(&cue.payload),
+        }
+    }
+}
+
+/// The header of a `WebVttRef`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VttHeaderRef<'a> {
+    /// An optional description of the WebVTT content.
+    pub description: Option<Cow<'a, str>>,
+    /// The metadata key-value pairs, in the order they appear.
+    pub metadata: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+}
+
+/// A WebVTT file borrowing its text from the parsed source.
+///
+/// Parsing allocates only the lists of cues, style blocks and metadata;
+/// identifiers, payloads and style sheets point into the source.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebVttRef<'a> {
+    /// The header of the WebVTT file.
+    pub header: VttHeaderRef<'a>,
+    /// The CSS of the STYLE blocks within the WebVTT file.
+    pub styles: Vec<Cow<'a, str>>,
+    /// The collection of cues within the WebVTT file.
+    pub cues: Vec<VttCueRef<'a>>,
+}
+
+impl<'a> WebVttRef<'a> {
+    /// Parses a WebVTT file, borrowing from the source where possible.
+    ///
+    /// Accepts the same input as `WebVtt::from_str`.
+    pub fn parse(s: &'a str) -> Result<Self, VttParseError> {
+        let s = strip_bom(s);
+        let mut lines = lines(s).peekable();
+        let first_line = lines.next().ok_or(VttParseError::EmptyInput)?;
+
+        // The signature is `WEBVTT`, alone or followed by a space or tab and
+        // a description.
+        let mut header = VttHeaderRef::default();
+        match first_line.strip_prefix("WEBVTT") {
+            Some("") => {}
+            Some(rest) if rest.starts_with([' ', '\t']) => {
+                let description = rest.trim();
+                if !description.is_empty() {
+                    header.description = Some(Cow::Borrowed(description));
+                }
+            }
+            _ => return Err(VttParseError::MissingHeader),
+        }
+
+        // Header lines are free-form and run up to the first empty line, or
+        // up to a cue timing line, which starts the first block. Lines of the
+        // form `key: value` are kept as metadata.
+        while let Some(line) = lines.next_if(|line| !line.contains("-->")) {
+            if line.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                header
+                    .metadata
+                    .push((Cow::Borrowed(key.trim()), Cow::Borrowed(value.trim())));
+            }
+        }
+
+        // Blocks are separated by empty lines. The lines of the current block
+        // are kept with their offsets, reusing one buffer for all blocks.
+        let mut styles = Vec::new();
+        let mut cues = Vec::new();
+        let mut block: Vec<(usize, &'a str)> = Vec::new();
+
+        while lines.peek().is_some() {
+            block.clear();
+            for line in &mut lines {
+                if line.is_empty() {
+                    if block.is_empty() {
+                        continue;
+                    }
+                    break;
+                }
+                block.push((offset(s, line), line));
+            }
+
+            let Some(&(_, first)) = block.first() else {
+                break;
+            };
+            if is_block_keyword(first, "NOTE") {
+                continue;
+            }
+            if is_block_keyword(first, "STYLE")
+                && cues.is_empty()
+                && !block.iter().any(|(_, line)| line.contains("-->"))
+            {
+                styles.push(join_lines(s, &block[1..]));
+                continue;
+            }
+            cues.push(parse_cue(s, &block)?);
+        }
+
+        Ok(WebVttRef {
+            header,
+            styles,
+            cues,
+        })
+    }
+
+    /// Converts the file into an owned `WebVtt`.
+    pub fn into_owned(self) -> WebVtt {
+        WebVtt {
+            header: VttHeader {
+                description: self.header.description.map(Cow::into_owned),
+                metadata: self
+                    .header
+                    .metadata
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect(),
+            },
+            styles: self.styles.into_iter().map(Cow::into_owned).collect(),
+            cues: self.cues.into_iter().map(VttCueRef::into_owned).collect(),
+        }
+    }
+}
+
+impl<'a> From<&'a WebVtt> for WebVttRef<'a> {
+    fn from(vtt: &'a WebVtt) -> Self {
+        WebVttRef {
+            header: VttHeaderRef {
+                description: vtt.header.description.as_deref().map(Cow::Borrowed),
+                metadata: vtt
+                    .header
+                    .metadata
+                    .iter()
+                    .map(|(key, value)| {
+                        (Cow::Borrowed(key.as_str()), Cow::Borrowed(value.as_str()))
+                    })
+                    .collect(),
+            },
+            styles: vtt
+                .styles
+                .iter()
+                .map(|s| Cow::Borrowed(s.as_str()))
+                .collect(),
+            cues: vtt.cues.iter().map(VttCueRef::from).collect(),
+        }
+    }
+}
+
+impl From<WebVttRef<'_>> for WebVtt {
+    fn from(vtt: WebVttRef<'_>) -> Self {
+        vtt.into_owned()
+    }
+}
+
+/// Parses a cue from its lines, each given with its offset in `source`.
+pub(crate) fn parse_cue<'a>(
+    source: &'a str,
+    lines: &[(usize, &'a str)],
+) -> Result<VttCueRef<'a>, VttParseError> {
+    let (&(_, first_line), mut rest) = lines.split_first().ok_or(VttParseError::EmptyInput)?;
+
+    let identifier = if !first_line.contains("-->") {
+        Some(Cow::Borrowed(first_line))
+    } else {
+        None
+    };
+
+    let timing_line = if identifier.is_some() {
+        let (&(_, line), payload) = rest.split_first().ok_or(VttParseError::MissingTiming)?;
+        rest = payload;
+        line
+    } else {
+        first_line
+    };
+
+    let invalid_timing = || VttParseError::InvalidTiming(timing_line.into());
+    let (start, end_and_settings) = timing_line.split_once("-->").ok_or_else(invalid_timing)?;
+    if end_and_settings.contains("-->") {
+        return Err(invalid_timing());
+    }
+
+    let start = VttTimestamp::from_str(start.trim())?;
+    let end_and_settings = end_and_settings.trim();
+    let (end, settings_str) = end_and_settings
+        .split_once(char::is_whitespace)
+        .unwrap_or((end_and_settings, ""));
+    if end.is_empty() {
+        return Err(invalid_timing());
+    }
+    let end = VttTimestamp::from_str(end)?;
+
+    let settings = if !settings_str.trim().is_empty() {
+        Some(parse_settings(settings_str)?)
+    } else {
+        None
+    };
+
+    Ok(VttCueRef {
+        identifier,
+        start,
+        end,
+        settings,
+        payload: join_lines(source, rest),
+    })
+}
+
+/// Returns the byte offset of a subslice of `source`.
+pub(crate) fn offset(source: &str, part: &str) -> usize {
+    part.as_ptr() as usize - source.as_ptr() as usize
+}
+
+/// Joins lines with `\n`, borrowing from `source` when they are already
+/// separated that way there.
+fn join_lines<'a>(source: &'a str, lines: &[(usize, &'a str)]) -> Cow<'a, str> {
+    let (Some(&(start, _)), Some(&(last_start, last))) = (lines.first(), lines.last()) else {
+        return Cow::Borrowed("");
+    };
+    let joined_len = lines.iter().map(|(_, line)| line.len()).sum::<usize>() + lines.len() - 1;
+    let separated_by_lf = lines
+        .windows(2)
+        .all(|pair| source[pair[0].0 + pair[0].1.len()..pair[1].0] == *"\n");
+    if separated_by_lf {
+        Cow::Borrowed(&source[start..last_start + last.len()])
+    } else {
+        let mut joined = String::with_capacity(joined_len);
+        for (i, (_, line)) in lines.iter().enumerate() {
+            if i > 0 {
+                joined.push('\n');
+            }
+            joined.push_str(line);
+        }
+        Cow::Owned(joined)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const VTT: &str = "WEBVTT - Test
+Kind: captions
+
+STYLE
+::cue { color: red }
+
+NOTE a comment
+
+intro
+00:00:01.000 --> 00:00:02.000 line:0
+Hello
+world
+
+00:00:03.000 --> 00:00:04.000
+Bye";
+
+    #[test]
+    fn test_parse_borrowed() {
+        let vtt = WebVttRef::parse(VTT).unwrap();
+        assert!(matches!(
+            vtt.styles[0],
+            Cow::Borrowed("::cue { color: red }")
+        ));
+        assert!(matches!(
+            vtt.cues[0].identifier,
+            Some(Cow::Borrowed("intro"))
+        ));
+        assert!(matches!(vtt.cues[0].payload, Cow::Borrowed("Hello\nworld")));
+        assert_eq!(
+            vtt.header.metadata,
+            vec![(Cow::Borrowed("Kind"), Cow::Borrowed("captions"))]
+        );
+        assert_eq!(vtt.into_owned(), WebVtt::from_str(VTT).unwrap());
+    }
+
+    #[test]
+    fn test_parse_crlf() {
+        let source = VTT.replace('\n', "\r\n");
+        let vtt = WebVttRef::parse(&source).unwrap();
+        assert!(matches!(vtt.cues[0].payload, Cow::Owned(_)));
+        assert!(matches!(vtt.cues[1].payload, Cow::Borrowed("Bye")));
+        assert_eq!(vtt.cues[0].payload, "Hello\nworld");
+    }
+
+    #[test]
+    fn test_borrow_owned() {
+        let owned = WebVtt::from_str(VTT).unwrap();
+        let borrowed = WebVttRef::from(&owned);
+        assert_eq!(WebVtt::from(borrowed), owned);
+    }
+}
@@ -0,0 +1,507 @@
+//! Semantic comparison of two WebVTT files.
+
+use crate::markup::plain_text;
+use crate::{CueIndex, VttCue, VttSettings, VttTimestamp, WebVtt};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A difference between two matched cues.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CueEdit {
+    /// The identifier changed.
+    IdentifierChanged {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// The cue was moved in time. Deltas are in milliseconds, positive when
+    /// the new time is later.
+    Retimed { start_delta: i64, end_delta: i64 },
+    /// The payload changed.
+    TextChanged { old: String, new: String },
+    /// The cue settings changed.
+    SettingsChanged {
+        old: Option<VttSettings>,
+        new: Option<VttSettings>,
+    },
+}
+
+/// A change between two WebVTT files, as found by `WebVtt::diff`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CueChange {
+    /// A cue only present in the new file, with its index there.
+    Added { new: usize, cue: VttCue },
+    /// A cue only present in the old file, with its index there.
+    Removed { old: usize, cue: VttCue },
+    /// A cue present in both files with differences.
+    Changed {
+        old: usize,
+        new: usize,
+        edits: Vec<CueEdit>,
+    },
+}
+
+/// The differences between two WebVTT files.
+///
+/// The `Display` implementation renders one line per difference, prefixed
+/// with `+`, `-` or `~`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VttDiff {
+    /// The changes, ordered by time.
+    pub changes: Vec<CueChange>,
+}
+
+impl VttDiff {
+    /// Returns `true` if the files have the same cues.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl WebVtt {
+    /// Compares the cues of this file with those of a newer version.
+    ///
+    /// Cues are matched by identifier first, then by time overlap and text
+    /// similarity, so retimed and edited cues are reported as changes rather
+    /// than as removed and added cues. The header and styles are not compared.
+    pub fn diff(&self, new: &WebVtt) -> VttDiff {
+        let matches = match_cues(&self.cues, &new.cues);
+
+        let mut changes: Vec<(core::time::Duration, CueChange)> = Vec::new();
+        let mut matched_old = vec![false; self.cues.len()];
+        let mut matched_new = vec![false; new.cues.len()];
+
+        for &(i, j) in &matches {
+            matched_old[i] = true;
+            matched_new[j] = true;
+            let edits = cue_edits(&self.cues[i], &new.cues[j]);
+            if !edits.is_empty() {
+                let change = CueChange::Changed {
+                    old: i,
+                    new: j,
+                    edits,
+                };
+                changes.push((new.cues[j].start.as_duration(), change));
+            }
+        }
+        for (i, cue) in self
+            .cues
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !matched_old[*i])
+        {
+            let change = CueChange::Removed {
+                old: i,
+                cue: cue.clone(),
+            };
+            changes.push((cue.start.as_duration(), change));
+        }
+        for (j, cue) in new
+            .cues
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| !matched_new[*j])
+        {
+            let change = CueChange::Added {
+                new: j,
+                cue: cue.clone(),
+            };
+            changes.push((cue.start.as_duration(), change));
+        }
+
+        changes.sort_by_key(|(time, _)| *time);
+        VttDiff {
+            changes: changes.into_iter().map(|(_, change)| change).collect(),
+        }
+    }
+}
+
+/// Returns the differences between two cues.
+pub(crate) fn cue_edits(old: &VttCue, new: &VttCue) -> Vec<CueEdit> {
+    let mut edits = Vec::new();
+    if old.identifier != new.identifier {
+        edits.push(CueEdit::IdentifierChanged {
+            old: old.identifier.clone(),
+            new: new.identifier.clone(),
+        });
+    }
+    if old.start != new.start || old.end != new.end {
+        let millis = |t: &crate::VttTimestamp| t.as_duration().as_millis() as i64;
+        edits.push(CueEdit::Retimed {
+            start_delta: millis(&new.start) - millis(&old.start),
+            end_delta: millis(&new.end) - millis(&old.end),
+        });
+    }
+    if old.payload.trim() != new.payload.trim() {
+        edits.push(CueEdit::TextChanged {
+            old: old.payload.trim().to_string(),
+            new: new.payload.trim().to_string(),
+        });
+    }
+    if normalized(&old.settings) != normalized(&new.settings) {
+        edits.push(CueEdit::SettingsChanged {
+            old: old.settings.clone(),
+            new: new.settings.clone(),
+        });
+    }
+    edits
+}
+
+/// Treats empty settings as no settings.
+fn normalized(settings: &Option<VttSettings>) -> Option<&VttSettings> {
+    settings.as_ref().filter(|s| **s != VttSettings::default())
+}
+
+/// Matches the cues of two versions of a file, returning pairs of indices.
+///
+/// Cues with the same identifier, unique in both versions, are matched first.
+/// The remaining cues are matched greedily by time overlap and text
+/// similarity, starting with cues of the same timing and text. Cues with
+/// identical text match even without overlap.
+pub(crate) fn match_cues(old: &[VttCue], new: &[VttCue]) -> Vec<(usize, usize)> {
+    fn unique_ids(cues: &[VttCue]) -> BTreeMap<&str, Option<usize>> {
+        let mut ids: BTreeMap<&str, Option<usize>> = BTreeMap::new();
+        for (i, cue) in cues.iter().enumerate() {
+            if let Some(ref id) = cue.identifier {
+                ids.entry(id)
+                    .and_modify(|index| *index = None)
+                    .or_insert(Some(i));
+            }
+        }
+        ids
+    }
+
+    let mut matches = Vec::new();
+    let mut old_free = vec![true; old.len()];
+    let mut new_free = vec![true; new.len()];
+
+    let new_ids = unique_ids(new);
+    for (id, i) in unique_ids(old) {
+        if let (Some(i), Some(Some(j))) = (i, new_ids.get(id)) {
+            matches.push((i, *j));
+            old_free[i] = false;
+            new_free[*j] = false;
+        }
+    }
+
+    let old_words: Vec<Vec<String>> = old.iter().map(words).collect();
+    let new_words: Vec<Vec<String>> = new.iter().map(words).collect();
+
+    // Cues with the same timing and text score highest, so they are paired
+    // first, each old cue with the first such new cue. This keeps unchanged
+    // cues out of the pairwise comparison below.
+    type Exact<'a> = (VttTimestamp, VttTimestamp, &'a [String]);
+    let mut exact: BTreeMap<Exact<'_>, Vec<usize>> = BTreeMap::new();
+    for j in (0..new.len()).rev().filter(|&j| new_free[j]) {
+        let key = (new[j].start, new[j].end, new_words[j].as_slice());
+        exact.entry(key).or_default().push(j);
+    }
+    for i in 0..old.len() {
+        if !old_free[i] {
+            continue;
+        }
+        let key = (old[i].start, old[i].end, old_words[i].as_slice());
+        if let Some(j) = exact.get_mut(&key).and_then(Vec::pop) {
+            matches.push((i, j));
+            old_free[i] = false;
+            new_free[j] = false;
+        }
+    }
+
+    // Only cues overlapping in time are compared for similarity.
+    let index = CueIndex::new(new);
+    let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+    for i in (0..old.len()).filter(|&i| old_free[i]) {
+        for j in index.overlapping(&old[i].start, &old[i].end) {
+            if !new_free[j] {
+                continue;
+            }
+            let overlap = overlap_ratio(&old[i], &new[j]);
+            let similarity = similarity(&old_words[i], &new_words[j]);
+            if overlap > 0.0 && similarity >= 0.3 {
+                candidates.push((overlap + similarity, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    let split = candidates.partition_point(|&(score, _, _)| score >= 1.0);
+    for &(_, i, j) in &candidates[..split] {
+        if old_free[i] && new_free[j] {
+            matches.push((i, j));
+            old_free[i] = false;
+            new_free[j] = false;
+        }
+    }
+
+    // Cues with identical text but no overlap score exactly 1, so they come
+    // after the overlapping cues scoring at least as much. Each old cue takes
+    // the first free new cue with its text.
+    let mut same_text: BTreeMap<&[String], (usize, Vec<usize>)> = BTreeMap::new();
+    for (j, words) in new_words.iter().enumerate() {
+        same_text.entry(words).or_default().1.push(j);
+    }
+    for i in 0..old.len() {
+        if !old_free[i] {
+            continue;
+        }
+        let Some((next, js)) = same_text.get_mut(old_words[i].as_slice()) else {
+            continue;
+        };
+        // Taken cues stay taken, so they can be skipped for good.
+        while js.get(*next).is_some_and(|&j| !new_free[j]) {
+            *next += 1;
+        }
+        if let Some(&j) = js.get(*next) {
+            matches.push((i, j));
+            old_free[i] = false;
+            new_free[j] = false;
+        }
+    }
+
+    for &(_, i, j) in &candidates[split..] {
+        if old_free[i] && new_free[j] {
+            matches.push((i, j));
+            old_free[i] = false;
+            new_free[j] = false;
+        }
+    }
+
+    matches.sort();
+    matches
+}
+
+fn words(cue: &VttCue) -> Vec<String> {
+    plain_text(&cue.payload)
+        .split_whitespace()
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Returns the overlap of two cues relative to the time they cover together.
+fn overlap_ratio(a: &VttCue, b: &VttCue) -> f64 {
+    let start = a.start.max(b.start).as_duration();
+    let end = a.end.min(b.end).as_duration();
+    let union_start = a.start.min(b.start).as_duration();
+    let union_end = a.end.max(b.end).as_duration();
+    if end <= start || union_end <= union_start {
+        return 0.0;
+    }
+    (end - start).as_secs_f64() / (union_end - union_start).as_secs_f64()
+}
+
+/// Returns the similarity of two word sequences, from 0 to 1, based on their
+/// longest common subsequence.
+fn similarity(a: &[String], b: &[String]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let mut row = vec![0usize; b.len() + 1];
+    for word in a {
+        let mut diagonal = 0;
+        for (k, other) in b.iter().enumerate() {
+            let above = row[k + 1];
+            row[k + 1] = if word == other {
+                diagonal + 1
+            } else {
+                above.max(row[k])
+            };
+            diagonal = above;
+        }
+    }
+    2.0 * row[b.len()] as f64 / (a.len() + b.len()) as f64
+}
+
+fn signed_millis(delta: i64) -> String {
+    format!("{}{}ms", if delta >= 0 { "+" } else { "-" }, delta.abs())
+}
+
+impl fmt::Display for CueEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CueEdit::IdentifierChanged { old, new } => write!(
+                f,
+                "identifier {:?} -> {:?}",
+                old.as_deref().unwrap_or(""),
+                new.as_deref().unwrap_or("")
+            ),
+            CueEdit::Retimed {
+                start_delta,
+                end_delta,
+            } => write!(
+                f,
+                "retimed start {}, end {}",
+                signed_millis(*start_delta),
+                signed_millis(*end_delta)
+            ),
+            CueEdit::TextChanged { old, new } => write!(f, "text {:?} -> {:?}", old, new),
+            CueEdit::SettingsChanged { old, new } => {
+                let render = |s: &Option<VttSettings>| s.as_ref().map(|s| s.to_string());
+                write!(
+                    f,
+                    "settings {:?} -> {:?}",
+                    render(old).unwrap_or_default(),
+                    render(new).unwrap_or_default()
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for VttDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            match change {
+                CueChange::Added { new, cue } => writeln!(
+                    f,
+                    "+ cue {} ({} --> {}): {:?}",
+                    new,
+                    cue.start,
+                    cue.end,
+                    cue.payload.trim()
+                )?,
+                CueChange::Removed { old, cue } => writeln!(
+                    f,
+                    "- cue {} ({} --> {}): {:?}",
+                    old,
+                    cue.start,
+                    cue.end,
+                    cue.payload.trim()
+                )?,
+                CueChange::Changed { old, new, edits } => {
+                    for edit in edits {
+                        writeln!(f, "~ cue {} -> {}: {}", old, new, edit)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::FromStr;
+
+    const OLD: &str = "WEBVTT
+
+intro
+00:00:01.000 --> 00:00:02.000
+Welcome!
+
+00:00:03.000 --> 00:00:05.000
+The quick brown fox jumps
+
+00:00:06.000 --> 00:00:07.000
+over the lazy dog
+
+00:00:08.000 --> 00:00:09.000
+Goodbye";
+
+    const NEW: &str = "WEBVTT
+
+intro
+00:00:00.500 --> 00:00:02.000
+Welcome!
+
+00:00:03.250 --> 00:00:04.900 align:start
+The quick brown fox jumped
+
+00:00:06.000 --> 00:00:07.000
+over the lazy dog
+
+00:00:10.000 --> 00:00:11.000
+The end";
+
+    #[test]
+    fn test_diff() {
+        let old = WebVtt::from_str(OLD).unwrap();
+        let new = WebVtt::from_str(NEW).unwrap();
+        let diff = old.diff(&new);
+
+        assert_eq!(diff.changes.len(), 4);
+        assert_eq!(
+            diff.changes[0],
+            CueChange::Changed {
+                old: 0,
+                new: 0,
+                edits: vec![CueEdit::Retimed {
+                    start_delta: -500,
+                    end_delta: 0
+                }],
+            }
+        );
+        let CueChange::Changed { ref edits, .. } = diff.changes[1] else {
+            panic!("expected a changed cue");
+        };
+        assert_eq!(edits.len(), 3);
+        assert!(matches!(diff.changes[2], CueChange::Removed { old: 3, .. }));
+        assert!(matches!(diff.changes[3], CueChange::Added { new: 3, .. }));
+
+        assert_eq!(
+            diff.to_string(),
+            "~ cue 0 -> 0: retimed start -500ms, end +0ms
+~ cue 1 -> 1: retimed start +250ms, end -100ms
+~ cue 1 -> 1: text \"The quick brown fox jumps\" -> \"The quick brown fox jumped\"
+~ cue 1 -> 1: settings \"\" -> \"align:start\"
+- cue 3 (00:00:08.000 --> 00:00:09.000): \"Goodbye\"
++ cue 3 (00:00:10.000 --> 00:00:11.000): \"The end\"
+"
+        );
+    }
+
+    #[test]
+    fn test_diff_identical_and_shifted() {
+        let old = WebVtt::from_str(OLD).unwrap();
+        assert!(old.diff(&old).is_empty());
+
+        // Cues with the same text match even when moved far away.
+        let mut shifted = old.clone();
+        shifted.cues[3].start = crate::VttTimestamp::new(core::time::Duration::from_secs(20));
+        shifted.cues[3].end = crate::VttTimestamp::new(core::time::Duration::from_secs(21));
+        let diff = old.diff(&shifted);
+        assert_eq!(diff.changes.len(), 1);
+        assert!(matches!(
+            diff.changes[0],
+            CueChange::Changed { old: 3, new: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn test_diff_large() {
+        let mut old = WebVtt::new();
+        for i in 0..4000 {
+            let start = i * 2000;
+            let text = format!("line {}", i % 50);
+            old.cues
+                .push(crate::test_util::cue(start, start + 1500, &text));
+        }
+        assert!(old.diff(&old).is_empty());
+
+        // Every cue keeps its position when all of them are moved.
+        let mut shifted = old.clone();
+        shifted.shift_later(core::time::Duration::from_secs(10_800));
+        let diff = old.diff(&shifted);
+        assert_eq!(diff.changes.len(), 4000);
+        assert!(diff.changes.iter().enumerate().all(|(k, change)| matches!(
+            change,
+            CueChange::Changed { old, new, .. } if *old == k && *new == k
+        )));
+
+        // Edited cues are matched to the cue at the same time.
+        let mut edited = old.clone();
+        for cue in &mut edited.cues {
+            cue.payload.push_str(" again");
+        }
+        let diff = old.diff(&edited);
+        assert_eq!(diff.changes.len(), 4000);
+        assert!(diff.changes.iter().enumerate().all(|(k, change)| matches!(
+            change,
+            CueChange::Changed { old, new, .. } if *old == k && *new == k
+        )));
+    }
+}
@@ -0,0 +1,450 @@
+//! Splitting and merging of cues and documents.
+//!
+//! Identifiers are handled the same way by every operation: the halves of a
+//! split cue `id` become `id-1` and `id-2`, a merged cue keeps the first
+//! identifier present, and concatenation renames repeated identifiers by
+//! appending `-2`, `-3` and so on.
+
+use crate::markup::{self, Token};
+use crate::{VttCue, VttTimestamp, WebVtt};
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::ops::Range;
+use core::str::FromStr;
+use core::time::Duration;
+
+impl VttCue {
+    /// Returns how long the cue is displayed.
+    pub fn duration(&self) -> Duration {
+        self.end
+            .as_duration()
+            .saturating_sub(self.start.as_duration())
+    }
+
+    /// Splits the cue at the given time.
+    ///
+    /// The payload is divided at the word boundary closest to the same
+    /// proportion of its text. Returns `None` if `at` is not strictly inside the cue.
+    pub fn split_at_time(&self, at: &VttTimestamp) -> Option<(VttCue, VttCue)> {
+        if *at <= self.start || *at >= self.end {
+            return None;
+        }
+
+        let points = split_points(&self.payload);
+        let total = visible_len(&self.payload);
+        // Nanoseconds, since a cue built in code may be shorter than 1 ms.
+        let elapsed = (at.as_duration() - self.start.as_duration()).as_nanos();
+        let target = (total as u128 * elapsed / self.duration().as_nanos()) as usize;
+
+        let words = points.iter().filter(|p| p.at_space);
+        let offset = if words.clone().next().is_some() {
+            words.min_by_key(|p| p.before.abs_diff(target))
+        } else {
+            points.iter().min_by_key(|p| p.before.abs_diff(target))
+        }
+        .map_or(self.payload.len(), |p| p.offset);
+
+        Some(self.split_payload_at(offset, *at))
+    }
+
+    /// Splits the cue before the given payload line, counting from zero.
+    ///
+    /// Time is allocated in proportion to the text on each side. Returns `None`
+    /// if the split would leave either cue without text or time.
+    pub fn split_at_line(&self, line: usize) -> Option<(VttCue, VttCue)> {
+        let offset = self
+            .payload
+            .match_indices('\n')
+            .nth(line.checked_sub(1)?)
+            .map(|(i, _)| i + 1)?;
+        self.split_at_offset(offset)
+    }
+
+    /// Splits the cue before the given character of the payload.
+    ///
+    /// Positions inside a markup tag are moved past the tag. Time is allocated
+    /// in proportion to the text on each side. Returns `None` if the split would
+    /// leave either cue without text or time.
+    pub fn split_at_char(&self, index: usize) -> Option<(VttCue, VttCue)> {
+        let mut offset = self
+            .payload
+            .char_indices()
+            .nth(index)
+            .map_or(self.payload.len(), |(i, _)| i);
+        for (start, token) in markup::tokenize(&self.payload) {
+            if !matches!(token, Token::Text(_)) && start < offset {
+                offset = offset.max(start + token.raw().len());
+            }
+        }
+        self.split_at_offset(offset)
+    }
+
+    /// Merges this cue with another, covering the union of their time ranges.
+    ///
+    /// The payload of the earlier cue comes first. The earlier cue's identifier
+    /// and settings are kept, falling back to the identifier of the later cue.
+    pub fn merge(&self, other: &VttCue) -> VttCue {
+        let (first, second) = if other.start < self.start {
+            (other, self)
+        } else {
+            (self, other)
+        };
+
+        let payload = match (first.payload.trim(), second.payload.trim()) {
+            ("", text) | (text, "") => text.to_string(),
+            (a, b) => format!("{}\n{}", a, b),
+        };
+
+        VttCue {
+            identifier: first
+                .identifier
+                .clone()
+                .or_else(|| second.identifier.clone()),
+            start: first.start,
+            end: first.end.max(second.end),
+            settings: first.settings.clone(),
+            payload,
+        }
+    }
+
+    fn split_at_offset(&self, offset: usize) -> Option<(VttCue, VttCue)> {
+        let (before, after) = self.payload.split_at(offset);
+        if before.trim().is_empty() || after.trim().is_empty() {
+            return None;
+        }
+
+        let total = visible_len(&self.payload) as u128;
+        let start = self.start.as_duration().as_millis();
+        let length = self.duration().as_millis();
+        let elapsed = (length * visible_len(before) as u128)
+            .checked_div(total)
+            .unwrap_or(length / 2);
+        let millis = start + elapsed;
+        let at = VttTimestamp::new(Duration::from_millis(millis as u64));
+        if at <= self.start || at >= self.end {
+            return None;
+        }
+
+        Some(self.split_payload_at(offset, at))
+    }
+
+    fn split_payload_at(&self, offset: usize, at: VttTimestamp) -> (VttCue, VttCue) {
+        let (first_payload, second_payload) = markup::split_balanced(&self.payload, offset);
+        let identifier = |n: u32| self.identifier.as_ref().map(|id| format!("{}-{}", id, n));
+
+        let first = VttCue {
+            identifier: identifier(1),
+            start: self.start,
+            end: at,
+            settings: self.settings.clone(),
+            payload: first_payload,
+        };
+        let second = VttCue {
+            identifier: identifier(2),
+            start: at,
+            end: self.end,
+            settings: self.settings.clone(),
+            payload: second_payload,
+        };
+        (first, second)
+    }
+}
+
+/// A position between two characters of cue text where a payload may be split.
+struct SplitPoint {
+    /// Byte offset of the position in the payload.
+    offset: usize,
+    /// Number of visible characters before the position.
+    before: usize,
+    /// Whether the position is at whitespace, i.e. between words.
+    at_space: bool,
+}
+
+fn split_points(payload: &str) -> Vec<SplitPoint> {
+    let mut points = Vec::new();
+    let mut before = 0;
+    for (start, token) in markup::tokenize(payload) {
+        if let Token::Text(text) = token {
+            for (i, c) in text.char_indices() {
+                if c.is_whitespace() {
+                    points.push(SplitPoint {
+                        offset: start + i,
+                        before,
+                        at_space: true,
+                    });
+                } else {
+                    if before > 0 {
+                        points.push(SplitPoint {
+                            offset: start + i,
+                            before,
+                            at_space: false,
+                        });
+                    }
+                    before += 1;
+                }
+            }
+        }
+    }
+    points
+}
+
+/// Counts the non-whitespace characters of cue text outside of tags.
+fn visible_len(payload: &str) -> usize {
+    markup::tokenize(payload)
+        .into_iter()
+        .filter_map(|(_, token)| match token {
+            Token::Text(text) => Some(text.chars().filter(|c| !c.is_whitespace()).count()),
+            _ => None,
+        })
+        .sum()
+}
+
+impl WebVtt {
+    /// Merges the cues in the given range into a single cue.
+    ///
+    /// Returns `false`, leaving the cues untouched, if the range is out of
+    /// bounds or holds fewer than two cues.
+    pub fn merge_cues(&mut self, range: Range<usize>) -> bool {
+        if range.end > self.cues.len() || range.len() < 2 {
+            return false;
+        }
+        let merged: Vec<VttCue> = self.cues.drain(range.clone()).collect();
+        let cue = merged[1..]
+            .iter()
+            .fold(merged[0].clone(), |acc, cue| acc.merge(cue));
+        self.cues.insert(range.start, cue);
+        true
+    }
+
+    /// Splits the WebVTT file into chunks of at most `max_cues` cues each.
+    ///
+    /// Every chunk keeps the header and styles of the original file.
+    pub fn chunks(&self, max_cues: usize) -> Vec<WebVtt> {
+        self.cues
+            .chunks(max_cues.max(1))
+            .map(|cues| WebVtt {
+                header: self.header.clone(),
+                styles: self.styles.clone(),
+                cues: cues.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Splits the WebVTT file into consecutive segments of the given length.
+    ///
+    /// Each cue is placed in the segment containing its start time and keeps its
+    /// timing. Every segment up to the last cue is returned, even if empty, so
+    /// the index of a segment is its number. Each empty segment still copies
+    /// the header and styles, so a length much shorter than the gaps between
+    /// cues costs memory in proportion to the duration of the file.
+    pub fn segments(&self, length: Duration) -> Vec<WebVtt> {
+        let length = length.as_millis().max(1);
+        let mut segments: Vec<WebVtt> = Vec::new();
+
+        for cue in &self.cues {
+            let n = (cue.start.as_duration().as_millis() / length) as usize;
+            while segments.len() <= n {
+                segments.push(WebVtt {
+                    header: self.header.clone(),
+                    styles: self.styles.clone(),
+                    cues: Vec::new(),
+                });
+            }
+            segments[n].cues.push(cue.clone());
+        }
+
+        segments
+    }
+
+    /// Moves every cue later by the given offset, including the timestamp tags
+    /// in payloads.
+    pub fn shift_later(&mut self, offset: Duration) {
+        self.retime(|t| t + offset);
+    }
+
+    /// Moves every cue earlier by the given offset, including the timestamp
+    /// tags in payloads.
+    ///
+    /// Times that would fall before zero become zero, and cues that would end
+    /// at zero are removed.
+    pub fn shift_earlier(&mut self, offset: Duration) {
+        self.retime(|t| t.saturating_sub(offset));
+        self.cues.retain(|cue| !cue.end.as_duration().is_zero());
+    }
+
+    fn retime(&mut self, map: impl Fn(Duration) -> Duration) {
+        let map_timestamp = |t: VttTimestamp| VttTimestamp::new(map(t.as_duration()));
+        for cue in &mut self.cues {
+            cue.start = map_timestamp(cue.start);
+            cue.end = map_timestamp(cue.end);
+            if cue.payload.contains('<') {
+                cue.payload = markup::tokenize(&cue.payload)
+                    .into_iter()
+                    .map(|(_, token)| match token {
+                        Token::Timestamp(raw) => {
+                            match VttTimestamp::from_str(&raw[1..raw.len() - 1]) {
+                                Ok(t) => format!("<{}>", map_timestamp(t)),
+                                Err(_) => raw.to_string(),
+                            }
+                        }
+                        _ => token.raw().to_string(),
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    /// Concatenates WebVTT files, shifting each one's cues by its offset.
+    ///
+    /// The header and styles of the first file are kept. Identifiers that were already used
+    /// by an earlier cue are made unique with a numeric suffix.
+    pub fn concat<I>(parts: I) -> WebVtt
+    where
+        I: IntoIterator<Item = (WebVtt, Duration)>,
+    {
+        let mut result: Option<WebVtt> = None;
+        let mut seen = BTreeSet::new();
+
+        for (part, offset) in parts {
+            let target = result.get_or_insert_with(|| WebVtt {
+                header: part.header.clone(),
+                styles: part.styles.clone(),
+                cues: Vec::new(),
+            });
+
+            for mut cue in part.cues {
+                cue.start = VttTimestamp::new(cue.start.as_duration() + offset);
+                cue.end = VttTimestamp::new(cue.end.as_duration() + offset);
+                if let Some(id) = cue.identifier.take() {
+                    let mut unique = id.clone();
+                    let mut n = 2;
+                    while seen.contains(&unique) {
+                        unique = format!("{}-{}", id, n);
+                        n += 1;
+                    }
+                    seen.insert(unique.clone());
+                    cue.identifier = Some(unique);
+                }
+                target.cues.push(cue);
+            }
+        }
+
+        result.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{cue, cue_with_id, ts};
+    use alloc::vec;
+
+    #[test]
+    fn test_shift() {
+        let mut vtt = WebVtt::new();
+        vtt.add_cue(cue(500, 1_500, "a"));
+        vtt.add_cue(cue(2_000, 4_000, "one <00:00:03.000>two"));
+
+        vtt.shift_later(Duration::from_millis(250));
+        assert_eq!((vtt.cues[0].start, vtt.cues[0].end), (ts(750), ts(1_750)));
+        assert_eq!(vtt.cues[1].payload, "one <00:00:03.250>two");
+
+        vtt.shift_earlier(Duration::from_millis(1_750));
+        assert_eq!(vtt.cues.len(), 1);
+        assert_eq!((vtt.cues[0].start, vtt.cues[0].end), (ts(500), ts(2_500)));
+        assert_eq!(vtt.cues[0].payload, "one <00:00:01.500>two");
+    }
+
+    #[test]
+    fn test_split_at_time() {
+        let c = cue_with_id("7", 0, 4_000, "one two three four");
+        let (a, b) = c.split_at_time(&ts(2_000)).unwrap();
+
+        assert_eq!(a.identifier.as_deref(), Some("7-1"));
+        assert_eq!(b.identifier.as_deref(), Some("7-2"));
+        assert_eq!((a.start, a.end), (ts(0), ts(2_000)));
+        assert_eq!((b.start, b.end), (ts(2_000), ts(4_000)));
+        assert_eq!(a.payload, "one two");
+        assert_eq!(b.payload, "three four");
+
+        assert!(c.split_at_time(&ts(0)).is_none());
+        assert!(c.split_at_time(&ts(4_000)).is_none());
+
+        // A cue shorter than a millisecond can still be split.
+        let short = VttCue {
+            end: VttTimestamp::new(Duration::from_micros(800)),
+            ..cue(0, 0, "one two")
+        };
+        let at = VttTimestamp::new(Duration::from_micros(400));
+        let (a, b) = short.split_at_time(&at).unwrap();
+        assert_eq!((a.payload.as_str(), b.payload.as_str()), ("one", "two"));
+    }
+
+    #[test]
+    fn test_split_at_line() {
+        let c = cue(1_000, 4_000, "<i>ab\ncdef</i>");
+        let (a, b) = c.split_at_line(1).unwrap();
+
+        assert_eq!(a.payload, "<i>ab</i>");
+        assert_eq!(b.payload, "<i>cdef</i>");
+        assert_eq!(a.end, ts(2_000));
+        assert_eq!(b.start, ts(2_000));
+        assert!(c.split_at_line(0).is_none());
+        assert!(c.split_at_line(2).is_none());
+    }
+
+    #[test]
+    fn test_split_at_char() {
+        let c = cue(0, 1_000, "<b>Hello</b> world");
+        assert!(c.split_at_char(0).is_none());
+
+        let (a, b) = c.split_at_char(12).unwrap();
+        assert_eq!(a.payload, "<b>Hello</b>");
+        assert_eq!(b.payload, "world");
+        assert_eq!(a.end, ts(500));
+    }
+
+    #[test]
+    fn test_merge_cues() {
+        let mut vtt = WebVtt::new();
+        vtt.add_cue(cue(0, 1_000, "a"));
+        vtt.add_cue(cue_with_id("b", 1_000, 2_000, "b"));
+        vtt.add_cue(cue(2_500, 3_000, "c"));
+
+        assert!(!vtt.merge_cues(2..4));
+        assert!(vtt.merge_cues(0..2));
+        assert_eq!(vtt.cues.len(), 2);
+        assert_eq!(vtt.cues[0], cue_with_id("b", 0, 2_000, "a\nb"));
+    }
+
+    #[test]
+    fn test_segments_and_concat() {
+        let mut vtt = WebVtt::new();
+        vtt.add_cue(cue_with_id("1", 0, 1_000, "a"));
+        vtt.add_cue(cue_with_id("2", 12_000, 13_000, "b"));
+
+        let segments = vtt.segments(Duration::from_secs(5));
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].cues.len(), 1);
+        assert!(segments[1].cues.is_empty());
+        assert_eq!(vtt.chunks(1).len(), 2);
+
+        let joined = WebVtt::concat([
+            (vtt.clone(), Duration::ZERO),
+            (vtt.clone(), Duration::from_secs(20)),
+        ]);
+        let ids: Vec<_> = joined.cues.iter().map(|c| c.identifier.clone()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                Some("1".to_string()),
+                Some("2".to_string()),
+                Some("1-2".to_string()),
+                Some("2-2".to_string())
+            ]
+        );
+        assert_eq!(joined.cues[3].start, ts(32_000));
+    }
+}
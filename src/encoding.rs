@@ -0,0 +1,233 @@
+//! Detection and decoding of text encodings on read.
+
+use crate::{VttParseError, WebVtt};
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+
+/// A text encoding of caption files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8, the only encoding WebVTT allows.
+    Utf8,
+    /// UTF-16, little endian.
+    Utf16Le,
+    /// UTF-16, big endian.
+    Utf16Be,
+    /// Windows-1252, the Western European code page of legacy SRT files.
+    Windows1252,
+    /// ISO-8859-1, also known as Latin-1.
+    Latin1,
+}
+
+/// The characters of Windows-1252 bytes 0x80 to 0x9F. Bytes that are
+/// undefined in the code page map to the C1 control with the same value.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+impl Encoding {
+    /// Decodes text in this encoding.
+    ///
+    /// A byte-order mark is kept as U+FEFF, which the parser skips. Fails on
+    /// invalid UTF-8 and on truncated or unpaired UTF-16; the single-byte
+    /// encodings decode any input.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>, VttParseError> {
+        match self {
+            Encoding::Utf8 => core::str::from_utf8(bytes).map(Cow::Borrowed).map_err(|e| {
+                VttParseError::InvalidEncoding(format!("invalid UTF-8 at byte {}", e.valid_up_to()))
+            }),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                if bytes.len() % 2 != 0 {
+                    return Err(VttParseError::InvalidEncoding(format!(
+                        "{} input has an odd number of bytes",
+                        self
+                    )));
+                }
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| match self {
+                        Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                        _ => u16::from_be_bytes([pair[0], pair[1]]),
+                    })
+                    .collect();
+                let mut text = String::with_capacity(bytes.len());
+                for c in char::decode_utf16(units.iter().copied()) {
+                    match c {
+                        Ok(c) => text.push(c),
+                        Err(_) => {
+                            // Everything before the surrogate decoded, so its
+                            // position is the UTF-16 length of the text so far.
+                            let position = text.encode_utf16().count() * 2;
+                            return Err(VttParseError::InvalidEncoding(format!(
+                                "unpaired surrogate in {} at byte {}",
+                                self, position
+                            )));
+                        }
+                    }
+                }
+                Ok(Cow::Owned(text))
+            }
+            Encoding::Windows1252 => Ok(Cow::Owned(
+                bytes
+                    .iter()
+                    .map(|&b| match b {
+                        0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(b - 0x80)],
+                        _ => char::from(b),
+                    })
+                    .collect(),
+            )),
+            Encoding::Latin1 => Ok(Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect())),
+        }
+    }
+
+    /// Detects the encoding of text and decodes it.
+    ///
+    /// UTF-16 is recognized by its byte-order mark. Other text is decoded as
+    /// UTF-8, or in the `fallback` encoding if it isn't valid UTF-8.
+    pub fn decode_detected(
+        bytes: &[u8],
+        fallback: Option<Encoding>,
+    ) -> Result<(Cow<'_, str>, Encoding), VttParseError> {
+        let encoding = match bytes {
+            [0xFF, 0xFE, ..] => Encoding::Utf16Le,
+            [0xFE, 0xFF, ..] => Encoding::Utf16Be,
+            _ => match (Encoding::Utf8.decode(bytes), fallback) {
+                (Ok(text), _) => return Ok((text, Encoding::Utf8)),
+                (Err(_), Some(fallback)) => fallback,
+                (Err(e), None) => return Err(e),
+            },
+        };
+        Ok((encoding.decode(bytes)?, encoding))
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "UTF-8"),
+            Encoding::Utf16Le => write!(f, "UTF-16LE"),
+            Encoding::Utf16Be => write!(f, "UTF-16BE"),
+            Encoding::Windows1252 => write!(f, "windows-1252"),
+            Encoding::Latin1 => write!(f, "ISO-8859-1"),
+        }
+    }
+}
+
+/// The error returned when parsing an `Encoding` from an unknown label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEncodingError(String);
+
+impl fmt::Display for ParseEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown encoding: {}", self.0)
+    }
+}
+
+impl Error for ParseEncodingError {}
+
+impl FromStr for Encoding {
+    type Err = ParseEncodingError;
+
+    /// Parses an encoding label such as `utf-8`, `utf-16le`, `windows-1252`,
+    /// `cp1252`, `iso-8859-1` or `latin1`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            "windows-1252" | "cp1252" => Ok(Encoding::Windows1252),
+            "iso-8859-1" | "latin1" => Ok(Encoding::Latin1),
+            _ => Err(ParseEncodingError(s.to_string())),
+        }
+    }
+}
+
+impl WebVtt {
+    /// Parses a `WebVtt` instance from bytes, detecting their encoding.
+    ///
+    /// See `Encoding::decode_detected` for how the encoding is chosen.
+    pub fn from_bytes(bytes: &[u8], fallback: Option<Encoding>) -> Result<Self, VttParseError> {
+        let (text, _) = Encoding::decode_detected(bytes, fallback)?;
+        Self::from_str(&text)
+    }
+
+    /// Creates a `WebVtt` instance by reading from any type that implements
+    /// `std::io::Read`, detecting the encoding like `WebVtt::from_bytes`.
+    #[cfg(feature = "std")]
+    pub fn from_reader_with_fallback<R: std::io::Read>(
+        mut reader: R,
+        fallback: Option<Encoding>,
+    ) -> Result<Self, crate::VttReadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(Self::from_bytes(&bytes, fallback)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    const VTT: &str = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\n\u{20AC}5 caf\u{E9}";
+
+    #[test]
+    fn test_detect_utf16() {
+        let expected = WebVtt::from_str(VTT).unwrap();
+        let le: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain(VTT.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let be: Vec<u8> = [0xFE, 0xFF]
+            .into_iter()
+            .chain(VTT.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        assert_eq!(WebVtt::from_bytes(&le, None).unwrap(), expected);
+        assert_eq!(WebVtt::from_bytes(&be, None).unwrap(), expected);
+        assert!(matches!(
+            WebVtt::from_bytes(&le[..le.len() - 1], None),
+            Err(VttParseError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn test_legacy_fallback() {
+        let mut bytes = b"WEBVTT\n\n00:00:01.000 --> 00:00:02.000\n".to_vec();
+        bytes.extend([0x80, b'5', b' ', b'c', b'a', b'f', 0xE9]);
+
+        match WebVtt::from_bytes(&bytes, None) {
+            Err(VttParseError::InvalidEncoding(message)) => {
+                assert_eq!(message, "invalid UTF-8 at byte 38")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let vtt = WebVtt::from_bytes(&bytes, Some(Encoding::Windows1252)).unwrap();
+        assert_eq!(vtt, WebVtt::from_str(VTT).unwrap());
+        let vtt = WebVtt::from_bytes(&bytes, Some(Encoding::Latin1)).unwrap();
+        assert_eq!(vtt.cues[0].payload, "\u{80}5 caf\u{E9}");
+
+        assert_eq!(Encoding::from_str("CP1252").unwrap(), Encoding::Windows1252);
+        assert_eq!(
+            Encoding::from_str("ebcdic").unwrap_err().to_string(),
+            "unknown encoding: ebcdic"
+        );
+    }
+
+    #[test]
+    fn test_unpaired_surrogate() {
+        let bytes = [0xFF, 0xFE, b'W', 0, 0x00, 0xD8, b'x', 0];
+        let error = Encoding::decode_detected(&bytes, None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid encoding: unpaired surrogate in UTF-16LE at byte 4"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Time-indexed lookup of cues.
+
+use crate::{VttCue, VttTimestamp, WebVtt};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// An interval index over the cues of a WebVTT file.
+///
+/// Cues are treated as half-open ranges `[start, end)`, so a cue is active at
+/// its start time but no longer active at its end time. Queries return cue
+/// indices into the `cues` the index was built from, in ascending order.
+#[derive(Debug, Clone, Default)]
+pub struct CueIndex {
+    /// Cue ranges sorted by start time.
+    entries: Vec<Entry>,
+    /// Implicit segment tree holding the maximum end time of each subtree.
+    max_end: Vec<Duration>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    start: Duration,
+    end: Duration,
+    cue: usize,
+}
+
+impl CueIndex {
+    /// Builds an index over the given cues.
+    pub fn new(cues: &[VttCue]) -> Self {
+        let mut entries: Vec<Entry> = cues
+            .iter()
+            .enumerate()
+            .map(|(cue, c)| Entry {
+                start: c.start.as_duration(),
+                end: c.end.as_duration(),
+                cue,
+            })
+            .collect();
+        entries.sort_by_key(|e| (e.start, e.cue));
+
+        let mut max_end = vec![Duration::ZERO; 4 * entries.len().max(1)];
+        if !entries.is_empty() {
+            build(&entries, &mut max_end, 1, 0, entries.len());
+        }
+
+        CueIndex { entries, max_end }
+    }
+
+    /// Returns the number of indexed cues.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the index contains no cues.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the indices of all cues active at the given time.
+    pub fn active_at(&self, time: &VttTimestamp) -> Vec<usize> {
+        let t = time.as_duration();
+        // Cues starting at or before `t` that end after it.
+        let upper = self.entries.partition_point(|e| e.start <= t);
+        self.collect(upper, t)
+    }
+
+    /// Returns the indices of all cues overlapping the half-open range `[start, end)`.
+    pub fn overlapping(&self, start: &VttTimestamp, end: &VttTimestamp) -> Vec<usize> {
+        let (a, b) = (start.as_duration(), end.as_duration());
+        if a >= b {
+            return Vec::new();
+        }
+        // Cues starting before `b` that end after `a`.
+        let upper = self.entries.partition_point(|e| e.start < b);
+        self.collect(upper, a)
+    }
+
+    /// Collects cues among the first `upper` entries whose end lies after `after`.
+    fn collect(&self, upper: usize, after: Duration) -> Vec<usize> {
+        let mut found = Vec::new();
+        if upper > 0 {
+            self.search(1, 0, self.entries.len(), upper, after, &mut found);
+        }
+        found.sort_unstable();
+        found
+    }
+
+    fn search(
+        &self,
+        node: usize,
+        lo: usize,
+        hi: usize,
+        upper: usize,
+        after: Duration,
+        found: &mut Vec<usize>,
+    ) {
+        if lo >= upper || self.max_end[node] <= after {
+            return;
+        }
+        if hi - lo == 1 {
+            found.push(self.entries[lo].cue);
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        self.search(2 * node, lo, mid, upper, after, found);
+        self.search(2 * node + 1, mid, hi, upper, after, found);
+    }
+}
+
+fn build(entries: &[Entry], max_end: &mut [Duration], node: usize, lo: usize, hi: usize) {
+    if hi - lo == 1 {
+        max_end[node] = entries[lo].end;
+        return;
+    }
+    let mid = (lo + hi) / 2;
+    build(entries, max_end, 2 * node, lo, mid);
+    build(entries, max_end, 2 * node + 1, mid, hi);
+    max_end[node] = max_end[2 * node].max(max_end[2 * node + 1]);
+}
+
+impl WebVtt {
+    /// Builds a `CueIndex` over the cues of the WebVTT file.
+    pub fn index(&self) -> CueIndex {
+        CueIndex::new(&self.cues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{cue, ts};
+
+    #[test]
+    fn test_active_at() {
+        let mut vtt = WebVtt::new();
+        vtt.add_cue(cue(5_000, 8_000, ""));
+        vtt.add_cue(cue(0, 2_000, ""));
+        vtt.add_cue(cue(1_000, 10_000, ""));
+        let index = vtt.index();
+
+        assert_eq!(index.active_at(&ts(0)), vec![1]);
+        assert_eq!(index.active_at(&ts(1_500)), vec![1, 2]);
+        assert_eq!(index.active_at(&ts(2_000)), vec![2]);
+        assert_eq!(index.active_at(&ts(5_000)), vec![0, 2]);
+        assert!(index.active_at(&ts(10_000)).is_empty());
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let cues = vec![
+            cue(0, 1_000, ""),
+            cue(1_000, 2_000, ""),
+            cue(2_000, 3_000, ""),
+        ];
+        let index = CueIndex::new(&cues);
+
+        assert_eq!(index.overlapping(&ts(500), &ts(1_500)), vec![0, 1]);
+        assert_eq!(index.overlapping(&ts(1_000), &ts(2_000)), vec![1]);
+        assert_eq!(index.overlapping(&ts(0), &ts(5_000)), vec![0, 1, 2]);
+        assert!(index.overlapping(&ts(3_000), &ts(4_000)).is_empty());
+        assert!(index.overlapping(&ts(1_500), &ts(1_500)).is_empty());
+    }
+
+    #[test]
+    fn test_matches_linear_scan() {
+        let cues: Vec<VttCue> = (0..200u64)
+            .map(|i| {
+                cue(
+                    (i * 7_919) % 60_000,
+                    (i * 7_919) % 60_000 + (i % 13 + 1) * 500,
+                    "",
+                )
+            })
+            .collect();
+        let index = CueIndex::new(&cues);
+
+        for t in (0..70_000).step_by(250) {
+            let expected: Vec<usize> = cues
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.start <= ts(t) && ts(t) < c.end)
+                .map(|(i, _)| i)
+                .collect();
+            assert_eq!(index.active_at(&ts(t)), expected);
+        }
+    }
+
+    #[test]
+    fn test_empty_index() {
+        let index = WebVtt::new().index();
+        assert!(index.is_empty());
+        assert!(index.active_at(&ts(0)).is_empty());
+    }
+}
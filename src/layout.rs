@@ -0,0 +1,638 @@
+//! Layout of cue boxes following the WebVTT rendering rules.
+//!
+//! This implements the "rules for updating the display of WebVTT text tracks"
+//! for a single text track without regions, using a monospace font model so
+//! the results are deterministic.
+
+use crate::{AlignSetting, LineSetting, VerticalSetting, VttCue, VttTimestamp, WebVtt};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Tolerance for comparing positions.
+const EPSILON: f64 = 1e-9;
+
+/// The size of the video rendering area, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Width of the rendering area.
+    pub width: f64,
+    /// Height of the rendering area.
+    pub height: f64,
+}
+
+impl Viewport {
+    /// Creates a new `Viewport`.
+    pub fn new(width: f64, height: f64) -> Self {
+        Viewport { width, height }
+    }
+}
+
+/// A monospace font model used to measure cue text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    /// The advance of every character along the line, in pixels.
+    pub char_width: f64,
+    /// The height of a line, in pixels.
+    pub line_height: f64,
+}
+
+impl FontMetrics {
+    /// Creates a new `FontMetrics`.
+    pub fn new(char_width: f64, line_height: f64) -> Self {
+        FontMetrics {
+            char_width,
+            line_height,
+        }
+    }
+
+    /// Returns metrics for the default WebVTT font size of 5% of the viewport height.
+    pub fn for_viewport(viewport: &Viewport) -> Self {
+        let font_size = viewport.height * 0.05;
+        FontMetrics {
+            char_width: font_size * 0.6,
+            line_height: font_size * 1.2,
+        }
+    }
+}
+
+/// The direction in which the lines of a cue are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritingMode {
+    /// Horizontal lines stacked top to bottom.
+    Horizontal,
+    /// Vertical lines stacked right to left (`vertical:rl`).
+    VerticalGrowingLeft,
+    /// Vertical lines stacked left to right (`vertical:lr`).
+    VerticalGrowingRight,
+}
+
+/// A laid out line of cue text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineBox {
+    /// The text of the line.
+    pub text: String,
+    /// The characters of the cue's plain text shown on this line.
+    pub range: Range<usize>,
+    /// Left edge of the line.
+    pub x: f64,
+    /// Top edge of the line.
+    pub y: f64,
+    /// Width of the line.
+    pub width: f64,
+    /// Height of the line.
+    pub height: f64,
+}
+
+/// A laid out cue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueBox {
+    /// Index of the cue in the WebVTT file.
+    pub cue: usize,
+    /// The writing mode of the cue.
+    pub writing_mode: WritingMode,
+    /// Left edge of the cue box.
+    pub x: f64,
+    /// Top edge of the cue box.
+    pub y: f64,
+    /// Width of the cue box.
+    pub width: f64,
+    /// Height of the cue box.
+    pub height: f64,
+    /// The lines of the cue, in reading order.
+    pub lines: Vec<LineBox>,
+}
+
+/// Computes cue boxes for a viewport and font model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutEngine {
+    /// The video rendering area.
+    pub viewport: Viewport,
+    /// The font model used to measure text.
+    pub metrics: FontMetrics,
+}
+
+/// Alignment of a box or text along the line, after resolving the base direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    LineLeft,
+    Center,
+    LineRight,
+}
+
+/// A rectangle in line-relative coordinates: `inline` runs along the lines and
+/// `block` across them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    inline: f64,
+    block: f64,
+    inline_size: f64,
+    block_size: f64,
+}
+
+impl Rect {
+    fn overlaps(&self, other: &Rect) -> bool {
+        self.inline < other.inline + other.inline_size - EPSILON
+            && other.inline < self.inline + self.inline_size - EPSILON
+            && self.block < other.block + other.block_size - EPSILON
+            && other.block < self.block + self.block_size - EPSILON
+    }
+
+    fn moved(&self, offset: f64) -> Rect {
+        Rect {
+            block: self.block + offset,
+            ..*self
+        }
+    }
+}
+
+impl LayoutEngine {
+    /// Creates a new `LayoutEngine`.
+    pub fn new(viewport: Viewport, metrics: FontMetrics) -> Self {
+        LayoutEngine { viewport, metrics }
+    }
+
+    /// Lays out the given cues, each paired with its index in the WebVTT file.
+    ///
+    /// Cues must be given in text track cue order; earlier cues keep their
+    /// place and later cues are moved to avoid them.
+    pub fn layout(&self, cues: &[(usize, &VttCue)]) -> Vec<CueBox> {
+        let mut placed: Vec<(WritingMode, Vec<Rect>)> = Vec::new();
+        let mut boxes = Vec::with_capacity(cues.len());
+
+        for &(index, cue) in cues {
+            let cue_box = self.layout_cue(index, cue, &placed);
+            let lines = cue_box
+                .lines
+                .iter()
+                .map(|l| to_rect(cue_box.writing_mode, l.x, l.y, l.width, l.height))
+                .collect();
+            placed.push((cue_box.writing_mode, lines));
+            boxes.push(cue_box);
+        }
+
+        boxes
+    }
+
+    fn layout_cue(
+        &self,
+        index: usize,
+        cue: &VttCue,
+        placed: &[(WritingMode, Vec<Rect>)],
+    ) -> CueBox {
+        let settings = cue.settings.clone().unwrap_or_default();
+        let text = cue.plain_text();
+
+        let writing_mode = match settings.vertical {
+            None => WritingMode::Horizontal,
+            Some(VerticalSetting::RightToLeft) => WritingMode::VerticalGrowingLeft,
+            Some(VerticalSetting::LeftToRight) => WritingMode::VerticalGrowingRight,
+        };
+        let (inline_extent, block_extent) = match writing_mode {
+            WritingMode::Horizontal => (self.viewport.width, self.viewport.height),
+            _ => (self.viewport.height, self.viewport.width),
+        };
+
+        // Position along the line.
+        let align = settings.align.clone().unwrap_or(AlignSetting::Middle);
+        let rtl = is_rtl(&text);
+        let position_alignment = match align {
+            AlignSetting::Left => Alignment::LineLeft,
+            AlignSetting::Right => Alignment::LineRight,
+            AlignSetting::Start if rtl => Alignment::LineRight,
+            AlignSetting::Start => Alignment::LineLeft,
+            AlignSetting::End if rtl => Alignment::LineLeft,
+            AlignSetting::End => Alignment::LineRight,
+            AlignSetting::Middle => Alignment::Center,
+        };
+        let position = match (settings.position, &align) {
+            (Some(p), _) => f64::from(p.min(100)),
+            (None, AlignSetting::Left) => 0.0,
+            (None, AlignSetting::Right) => 100.0,
+            (None, _) => 50.0,
+        };
+        let max_size = match position_alignment {
+            Alignment::LineLeft => 100.0 - position,
+            Alignment::LineRight => position,
+            Alignment::Center if position <= 50.0 => position * 2.0,
+            Alignment::Center => (100.0 - position) * 2.0,
+        };
+        let size = settings
+            .size
+            .map_or(100.0, |s| f64::from(s.min(100)))
+            .min(max_size);
+        let box_inline = match position_alignment {
+            Alignment::LineLeft => position,
+            Alignment::LineRight => position - size,
+            Alignment::Center => position - size / 2.0,
+        } * inline_extent
+            / 100.0;
+        let box_inline_size = size * inline_extent / 100.0;
+
+        // Text lines within the box.
+        let text_alignment = match position_alignment {
+            Alignment::Center => Alignment::Center,
+            _ if matches!(align, AlignSetting::Middle) => Alignment::Center,
+            other => other,
+        };
+        // The cast truncates, which floors the positive value without std.
+        let capacity = (box_inline_size / self.metrics.char_width + EPSILON).max(1.0) as usize;
+        let step = self.metrics.line_height;
+        let mut lines: Vec<(String, Range<usize>, Rect)> = wrap(&text, capacity)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (line, range))| {
+                let inline_size = line.chars().count() as f64 * self.metrics.char_width;
+                let offset = match text_alignment {
+                    Alignment::LineLeft => 0.0,
+                    Alignment::Center => (box_inline_size - inline_size) / 2.0,
+                    Alignment::LineRight => box_inline_size - inline_size,
+                };
+                let rect = Rect {
+                    inline: box_inline + offset,
+                    block: i as f64 * step,
+                    inline_size,
+                    block_size: step,
+                };
+                (line, range, rect)
+            })
+            .collect();
+        let box_block_size = lines.len() as f64 * step;
+        if writing_mode == WritingMode::VerticalGrowingLeft {
+            // The first line is the rightmost one.
+            for (_, _, rect) in &mut lines {
+                rect.block = box_block_size - step - rect.block;
+            }
+        }
+
+        // Position across the lines.
+        let others: Vec<Rect> = placed
+            .iter()
+            .filter(|(mode, _)| {
+                (*mode == WritingMode::Horizontal) == (writing_mode == WritingMode::Horizontal)
+            })
+            .flat_map(|(_, rects)| rects.iter().copied())
+            .collect();
+        let rects: Vec<Rect> = lines.iter().map(|(_, _, r)| *r).collect();
+        let offset = match settings.line {
+            Some(LineSetting::Percentage(line)) => {
+                let specified = f64::from(line.min(100)) * block_extent / 100.0;
+                position_freely(&rects, &others, specified, block_extent)
+            }
+            Some(LineSetting::Number(line)) => snap_to_lines(
+                &rects,
+                &others,
+                i64::from(line),
+                writing_mode,
+                step,
+                block_extent,
+            ),
+            Some(LineSetting::Auto) | None => {
+                snap_to_lines(&rects, &others, -1, writing_mode, step, block_extent)
+            }
+        };
+
+        let cue_rect = Rect {
+            inline: box_inline,
+            block: offset,
+            inline_size: box_inline_size,
+            block_size: box_block_size,
+        };
+        let (x, y, width, height) = from_rect(writing_mode, &cue_rect);
+        let lines = lines
+            .into_iter()
+            .map(|(text, range, rect)| {
+                let (x, y, width, height) = from_rect(writing_mode, &rect.moved(offset));
+                LineBox {
+                    text,
+                    range,
+                    x,
+                    y,
+                    width,
+                    height,
+                }
+            })
+            .collect();
+
+        CueBox {
+            cue: index,
+            writing_mode,
+            x,
+            y,
+            width,
+            height,
+            lines,
+        }
+    }
+}
+
+/// Converts a box in physical coordinates to line-relative coordinates.
+fn to_rect(mode: WritingMode, x: f64, y: f64, width: f64, height: f64) -> Rect {
+    match mode {
+        WritingMode::Horizontal => Rect {
+            inline: x,
+            block: y,
+            inline_size: width,
+            block_size: height,
+        },
+        _ => Rect {
+            inline: y,
+            block: x,
+            inline_size: height,
+            block_size: width,
+        },
+    }
+}
+
+/// Converts a line-relative box to physical `(x, y, width, height)`.
+fn from_rect(mode: WritingMode, rect: &Rect) -> (f64, f64, f64, f64) {
+    match mode {
+        WritingMode::Horizontal => (rect.inline, rect.block, rect.inline_size, rect.block_size),
+        _ => (rect.block, rect.inline, rect.block_size, rect.inline_size),
+    }
+}
+
+/// Returns the block offset for a snap-to-lines cue, avoiding earlier cues.
+fn snap_to_lines(
+    rects: &[Rect],
+    others: &[Rect],
+    line: i64,
+    mode: WritingMode,
+    step: f64,
+    extent: f64,
+) -> f64 {
+    if step <= 0.0 || rects.is_empty() {
+        return 0.0;
+    }
+    let block_size = bounds(rects).block_size;
+
+    let mut line = line;
+    if mode == WritingMode::VerticalGrowingLeft {
+        line = -(line + 1);
+    }
+    let mut position = line as f64 * step;
+    if mode == WritingMode::VerticalGrowingLeft {
+        position = position - block_size + step;
+    }
+    let mut step = step;
+    if line < 0 {
+        position += extent;
+        step = -step;
+    }
+
+    let specified = position;
+    let mut best: Option<(f64, f64)> = None;
+    let mut switched = false;
+    // Bounded so that degenerate inputs still terminate.
+    for _ in 0..10_000 {
+        let moved: Vec<Rect> = rects.iter().map(|r| r.moved(position)).collect();
+        let outside = outside_fraction(&bounds(&moved), extent);
+        let overlapping = moved.iter().any(|r| others.iter().any(|o| r.overlaps(o)));
+        if !overlapping && outside <= EPSILON {
+            return position;
+        }
+
+        if best.is_none_or(|(_, score)| outside < score) {
+            best = Some((position, outside));
+        }
+
+        let area = bounds(&moved);
+        let leaving = if step > 0.0 {
+            area.block + area.block_size > extent + EPSILON
+        } else {
+            area.block < -EPSILON
+        };
+        if leaving {
+            if switched {
+                break;
+            }
+            position = specified;
+            step = -step;
+            switched = true;
+            continue;
+        }
+        position += step;
+    }
+
+    best.map_or(specified, |(position, _)| position)
+}
+
+/// Returns the block offset for a cue positioned by percentage, moved the
+/// shortest distance needed to avoid earlier cues while staying in view.
+fn position_freely(rects: &[Rect], others: &[Rect], specified: f64, extent: f64) -> f64 {
+    if rects.is_empty() {
+        return specified;
+    }
+    let block_size = bounds(rects).block_size;
+    let max = (extent - block_size).max(0.0);
+    let fits = |position: f64| {
+        !rects
+            .iter()
+            .map(|r| r.moved(position))
+            .any(|r| others.iter().any(|o| r.overlaps(o)))
+    };
+
+    let start = specified.clamp(0.0, max);
+    if fits(start) {
+        return start;
+    }
+
+    // Candidate positions are those touching an edge of another cue.
+    let mut candidates: Vec<f64> = vec![0.0, max];
+    for other in others {
+        for rect in rects {
+            candidates.push(other.block + other.block_size - rect.block);
+            candidates.push(other.block - rect.block_size - rect.block);
+        }
+    }
+    candidates
+        .into_iter()
+        .filter(|&p| p >= -EPSILON && p <= max + EPSILON && fits(p))
+        .min_by(|a, b| {
+            let (da, db) = ((a - start).abs(), (b - start).abs());
+            da.total_cmp(&db).then(a.total_cmp(b))
+        })
+        .unwrap_or(start)
+}
+
+/// Returns the bounding rectangle of the given rectangles.
+fn bounds(rects: &[Rect]) -> Rect {
+    let inline = rects.iter().map(|r| r.inline).fold(f64::INFINITY, f64::min);
+    let block = rects.iter().map(|r| r.block).fold(f64::INFINITY, f64::min);
+    let inline_end = rects
+        .iter()
+        .map(|r| r.inline + r.inline_size)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let block_end = rects
+        .iter()
+        .map(|r| r.block + r.block_size)
+        .fold(f64::NEG_INFINITY, f64::max);
+    Rect {
+        inline,
+        block,
+        inline_size: inline_end - inline,
+        block_size: block_end - block,
+    }
+}
+
+/// Returns the fraction of a rectangle lying outside `0..extent` across the lines.
+fn outside_fraction(rect: &Rect, extent: f64) -> f64 {
+    if rect.block_size <= 0.0 {
+        return 0.0;
+    }
+    let inside = (rect.block + rect.block_size).min(extent) - rect.block.max(0.0);
+    1.0 - inside.max(0.0) / rect.block_size
+}
+
+/// Returns `true` if the first strong character of the text is right-to-left.
+fn is_rtl(text: &str) -> bool {
+    text.chars()
+        .find(|c| c.is_alphabetic())
+        .is_some_and(|c| matches!(c, '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}'))
+}
+
+/// Wraps text into lines of at most `capacity` characters.
+///
+/// Lines break at explicit line breaks and between words; words longer than a
+/// line are broken. Each line is returned with its character range in `text`.
+fn wrap(text: &str, capacity: usize) -> Vec<(String, Range<usize>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    for (i, &c) in chars
+        .iter()
+        .enumerate()
+        .chain(core::iter::once((chars.len(), &'\n')))
+    {
+        if c != '\n' {
+            continue;
+        }
+        let mut pos = start;
+        loop {
+            // Skip spaces at the start of a wrapped line.
+            while pos < i && chars[pos] == ' ' && pos > start {
+                pos += 1;
+            }
+            if i - pos <= capacity {
+                lines.push((chars[pos..i].iter().collect(), pos..i));
+                break;
+            }
+            let limit = pos + capacity;
+            let end = (pos + 1..=limit)
+                .rev()
+                .find(|&e| chars[e] == ' ')
+                .unwrap_or(limit);
+            lines.push((chars[pos..end].iter().collect(), pos..end));
+            pos = end;
+        }
+        start = i + 1;
+    }
+
+    lines
+}
+
+impl WebVtt {
+    /// Lays out the cues active at the given time.
+    pub fn layout_at(&self, time: &VttTimestamp, engine: &LayoutEngine) -> Vec<CueBox> {
+        let mut active: Vec<usize> = self.index().active_at(time);
+        // Text track cue order: by start time, then by later end time first.
+        active.sort_by(|&a, &b| {
+            let (a_cue, b_cue) = (&self.cues[a], &self.cues[b]);
+            a_cue
+                .start
+                .cmp(&b_cue.start)
+                .then(b_cue.end.cmp(&a_cue.end))
+                .then(a.cmp(&b))
+        });
+        let cues: Vec<(usize, &VttCue)> = active.into_iter().map(|i| (i, &self.cues[i])).collect();
+        engine.layout(&cues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ts;
+    use core::str::FromStr;
+
+    fn engine() -> LayoutEngine {
+        LayoutEngine::new(Viewport::new(640.0, 360.0), FontMetrics::new(10.0, 20.0))
+    }
+
+    #[test]
+    fn test_default_cue_at_bottom_center() {
+        let vtt = WebVtt::from_str("WEBVTT\n\n00:00:00.000 --> 00:00:05.000\nHello").unwrap();
+        let boxes = vtt.layout_at(&ts(1_000), &engine());
+
+        assert_eq!(boxes.len(), 1);
+        let cue = &boxes[0];
+        assert_eq!(
+            (cue.x, cue.y, cue.width, cue.height),
+            (0.0, 340.0, 640.0, 20.0)
+        );
+        assert_eq!(cue.lines[0].text, "Hello");
+        assert_eq!((cue.lines[0].x, cue.lines[0].width), (295.0, 50.0));
+        assert_eq!(cue.lines[0].range, 0..5);
+    }
+
+    #[test]
+    fn test_wrapping_and_collision_avoidance() {
+        let vtt = WebVtt::from_str(
+            "WEBVTT
+
+00:00:00.000 --> 00:00:05.000
+First cue
+
+00:00:00.000 --> 00:00:05.000 size:20% align:start position:45%
+Second cue wraps here",
+        )
+        .unwrap();
+        let boxes = vtt.layout_at(&ts(1_000), &engine());
+
+        assert_eq!(boxes[0].y, 340.0);
+        let second = &boxes[1];
+        // 20% of 640 is 128 pixels, room for 12 characters.
+        let texts: Vec<&str> = second.lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["Second cue", "wraps here"]);
+        assert_eq!(second.lines[1].range, 11..21);
+        assert_eq!(second.x, 288.0);
+        assert_eq!(second.lines[0].x, 288.0);
+        // Two lines moved up until clear of the first cue.
+        assert_eq!((second.y, second.height), (300.0, 40.0));
+    }
+
+    #[test]
+    fn test_line_settings() {
+        let vtt = WebVtt::from_str(
+            "WEBVTT
+
+00:00:00.000 --> 00:00:05.000 line:0
+Top
+
+00:00:00.000 --> 00:00:05.000 line:50% align:right
+Middle",
+        )
+        .unwrap();
+        let boxes = vtt.layout_at(&ts(1_000), &engine());
+
+        assert_eq!(boxes[0].y, 0.0);
+        assert_eq!(boxes[1].y, 180.0);
+        assert_eq!(boxes[1].lines[0].x, 640.0 - 60.0);
+    }
+
+    #[test]
+    fn test_vertical_growing_left() {
+        let vtt =
+            WebVtt::from_str("WEBVTT\n\n00:00:00.000 --> 00:00:05.000 vertical:rl line:0\n縦書き")
+                .unwrap();
+        let boxes = vtt.layout_at(&ts(1_000), &engine());
+
+        let cue = &boxes[0];
+        assert_eq!(cue.writing_mode, WritingMode::VerticalGrowingLeft);
+        assert_eq!((cue.x, cue.width), (620.0, 20.0));
+        assert_eq!((cue.y, cue.height), (0.0, 360.0));
+        assert_eq!((cue.lines[0].y, cue.lines[0].height), (165.0, 30.0));
+    }
+}
@@ -1,12 +1,71 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use borrowed::{offset, parse_cue};
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+use line_ending::lines;
+#[cfg(feature = "serde")]
 use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
-use std::error::Error;
-use std::fmt;
-use std::str::FromStr;
-use std::time::Duration;
+
+mod bilingual;
+mod borrowed;
+mod diff;
+mod edit;
+mod encoding;
+mod index;
+mod layout;
+mod line_ending;
+mod lint;
+mod lossless;
+mod markup;
+mod merge;
+mod overlap;
+mod reflow;
+mod rolling;
+mod speaker;
+#[cfg(feature = "serde")]
+pub mod structured;
+mod svg;
+mod thumbnail;
+mod track;
+mod transcript;
+mod translate;
+
+pub use bilingual::{BilingualConfig, BilingualLayout, CueAlignment};
+pub use borrowed::{VttCueRef, VttHeaderRef, WebVttRef};
+pub use diff::{CueChange, CueEdit, VttDiff};
+pub use encoding::{Encoding, ParseEncodingError};
+pub use index::CueIndex;
+pub use layout::{CueBox, FontMetrics, LayoutEngine, LineBox, Viewport, WritingMode};
+pub use line_ending::{LineEnding, ParseLineEndingError};
+pub use lint::{LintConfig, LintFinding, LintIssue, LintLimit, Severity};
+pub use lossless::LosslessWebVtt;
+pub use markup::{parse_cue_text, CueNode, CueSpan, SpanKind};
+pub use merge::{ConflictKind, MergeConflict, MergeResult};
+pub use overlap::{CueOverlap, OverlapStrategy};
+pub use reflow::{LineBalance, ReflowConfig};
+pub use speaker::SpeakerStats;
+#[cfg(feature = "serde")]
+pub use structured::{PayloadFormat, Structured, StructuredOptions, TimeFormat};
+pub use svg::SvgOptions;
+pub use thumbnail::{SpriteConfig, SpriteGridError, Thumbnail};
+pub use track::{Chapter, MetadataCue};
+pub use transcript::TranscriptConfig;
 
 /// An error type representing possible parsing failures in WebVTT data.
+///
+/// More variants may be added in future releases, so matches on it need a
+/// wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum VttParseError {
     /// The provided data does not conform to the expected format.
     InvalidFormat,
@@ -22,8 +81,19 @@ pub enum VttParseError {
     InvalidSetting(String),
     /// The WebVTT header is missing.
     MissingHeader,
-    /// A metadata line is invalid.
+    /// A metadata line is invalid. No longer returned by the parser, which
+    /// skips header lines that aren't `key: value` pairs.
     InvalidMetadataLine(String),
+    /// The input could not be decoded as text.
+    InvalidEncoding(String),
+    /// The input contains no lines.
+    EmptyInput,
+    /// A cue block ends before its timing line.
+    MissingTiming,
+    /// The timing line of a cue is malformed.
+    InvalidTiming(String),
+    /// A translation file is malformed or uses unsupported features.
+    InvalidTranslation(String),
 }
 
 impl fmt::Display for VttParseError {
@@ -39,14 +109,68 @@ impl fmt::Display for VttParseError {
             VttParseError::InvalidMetadataLine(line) => {
                 write!(f, "Invalid metadata line: {}", line)
             }
+            VttParseError::InvalidEncoding(message) => write!(f, "Invalid encoding: {}", message),
+            VttParseError::EmptyInput => write!(f, "Empty input"),
+            VttParseError::MissingTiming => write!(f, "Missing cue timing line"),
+            VttParseError::InvalidTiming(line) => write!(f, "Invalid cue timing line: {}", line),
+            VttParseError::InvalidTranslation(message) => {
+                write!(f, "Invalid translation file: {}", message)
+            }
         }
     }
 }
 
 impl Error for VttParseError {}
 
+/// An error reading WebVTT data from a reader.
+///
+/// Failures of the reader itself are kept apart from invalid data, so callers
+/// can retry the former.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum VttReadError {
+    /// Reading from the underlying reader failed.
+    Io(std::io::Error),
+    /// The data read is not a valid WebVTT file.
+    Parse(VttParseError),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for VttReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VttReadError::Io(e) => write!(f, "I/O error: {}", e),
+            VttReadError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for VttReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VttReadError::Io(e) => Some(e),
+            VttReadError::Parse(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for VttReadError {
+    fn from(e: std::io::Error) -> Self {
+        VttReadError::Io(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<VttParseError> for VttReadError {
+    fn from(e: VttParseError) -> Self {
+        VttReadError::Parse(e)
+    }
+}
+
 /// Represents a timestamp in WebVTT format.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct VttTimestamp(Duration);
 
 impl VttTimestamp {
@@ -147,6 +271,7 @@ impl fmt::Display for VttTimestamp {
     }
 }
 
+#[cfg(feature = "serde")]
 impl Serialize for VttTimestamp {
     /// Serializes the `VttTimestamp` as a string.
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -157,6 +282,7 @@ impl Serialize for VttTimestamp {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for VttTimestamp {
     /// Deserializes a `VttTimestamp` from a string.
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -183,61 +309,30 @@ pub struct VttCue {
     pub payload: String,
 }
 
+impl VttCue {
+    /// Returns the text of the payload with markup tags removed and character
+    /// references decoded.
+    pub fn plain_text(&self) -> String {
+        markup::plain_text(&self.payload)
+    }
+
+    /// Parses the payload into a tree of cue text nodes.
+    pub fn nodes(&self) -> Vec<CueNode> {
+        markup::parse_cue_text(&self.payload)
+    }
+}
+
 impl FromStr for VttCue {
     type Err = VttParseError;
 
     /// Parses a `VttCue` from a string.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut lines = s.lines();
-        let first_line = lines.next().ok_or(VttParseError::InvalidFormat)?;
-
-        let identifier = if !first_line.contains("-->") {
-            Some(first_line.to_string())
-        } else {
-            None
-        };
-
-        let timing_line = if identifier.is_some() {
-            lines.next().ok_or(VttParseError::InvalidFormat)?
-        } else {
-            first_line
-        };
-
-        let timing_parts: Vec<&str> = timing_line.split("-->").collect();
-        if timing_parts.len() != 2 {
-            return Err(VttParseError::InvalidFormat);
-        }
-
-        let start = VttTimestamp::from_str(timing_parts[0].trim())?;
-        let end_and_settings = timing_parts[1].trim();
-
-        let mut end_part_and_settings = end_and_settings.split_whitespace();
-        let end_part = end_part_and_settings
-            .next()
-            .ok_or(VttParseError::InvalidFormat)?;
-        let end = VttTimestamp::from_str(end_part)?;
-
-        // Build settings string
-        let settings_str = end_part_and_settings.collect::<Vec<&str>>().join(" ");
-        let settings = if !settings_str.is_empty() {
-            Some(parse_settings(&settings_str)?)
-        } else {
-            None
-        };
-
-        // Collect remaining lines as payload
-        let payload = lines.collect::<Vec<&str>>().join("\n");
-
-        Ok(VttCue {
-            identifier,
-            start,
-            end,
-            settings,
-            payload,
-        })
+        let lines: Vec<(usize, &str)> = lines(s).map(|line| (offset(s, line), line)).collect();
+        parse_cue(s, &lines).map(VttCueRef::into_owned)
     }
 }
 
+#[cfg(feature = "serde")]
 impl Serialize for VttCue {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -249,6 +344,7 @@ impl Serialize for VttCue {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for VttCue {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -385,6 +481,7 @@ pub struct VttSettings {
     pub align: Option<AlignSetting>,
 }
 
+#[cfg(feature = "serde")]
 impl Serialize for VttSettings {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -395,6 +492,7 @@ impl Serialize for VttSettings {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for VttSettings {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -518,6 +616,12 @@ impl fmt::Display for AlignSetting {
 pub struct WebVtt {
     /// The header of the WebVTT file.
     pub header: VttHeader,
+    /// The CSS of the STYLE blocks within the WebVTT file.
+    ///
+    /// Only blocks before the first cue are style sheets; a later block
+    /// starting with `STYLE` is parsed as a cue. NOTE blocks are skipped when
+    /// parsing and not kept.
+    pub styles: Vec<String>,
     /// The collection of cues within the WebVTT file.
     pub cues: Vec<VttCue>,
 }
@@ -527,6 +631,7 @@ impl WebVtt {
     pub fn new() -> Self {
         Self {
             header: VttHeader::default(),
+            styles: Vec::new(),
             cues: Vec::new(),
         }
     }
@@ -544,17 +649,16 @@ impl WebVtt {
     }
 
     /// Creates a `WebVtt` instance by reading from any type that implements `std::io::Read`.
-    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Self, VttParseError> {
-        use std::io::Read;
-        let mut buffer = String::new();
-        let mut buf_reader = std::io::BufReader::new(reader);
-        buf_reader
-            .read_to_string(&mut buffer)
-            .map_err(|_| VttParseError::InvalidFormat)?;
-        Self::from_str(&buffer)
+    ///
+    /// UTF-16 input is recognized by its byte-order mark; other input must be UTF-8.
+    /// Errors of the reader are returned as `VttReadError::Io`.
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Self, VttReadError> {
+        Self::from_reader_with_fallback(reader, None)
     }
 }
 
+#[cfg(feature = "serde")]
 impl Serialize for WebVtt {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -565,6 +669,7 @@ impl Serialize for WebVtt {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for WebVtt {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -581,9 +686,10 @@ pub struct VttHeader {
     /// An optional description of the WebVTT content.
     pub description: Option<String>,
     /// A collection of metadata key-value pairs.
-    pub metadata: HashMap<String, String>,
+    pub metadata: BTreeMap<String, String>,
 }
 
+#[cfg(feature = "serde")]
 impl Serialize for VttHeader {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -601,6 +707,7 @@ impl Serialize for VttHeader {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for VttHeader {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -609,7 +716,7 @@ impl<'de> Deserialize<'de> for VttHeader {
         let s = String::deserialize(deserializer)?;
         let mut lines = s.lines();
         let description = lines.next().map(|line| line.trim().to_string());
-        let mut metadata = HashMap::new();
+        let mut metadata = BTreeMap::new();
         for line in lines {
             if let Some((key, value)) = line.split_once(':') {
                 metadata.insert(key.trim().to_string(), value.trim().to_string());
@@ -628,62 +735,20 @@ impl FromStr for WebVtt {
     type Err = VttParseError;
 
     /// Parses a `WebVtt` instance from a string.
+    ///
+    /// Blocks are separated by empty lines; a line of only whitespace belongs
+    /// to the block around it.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut lines = s.lines();
-        let first_line = lines.next().ok_or(VttParseError::InvalidFormat)?.trim();
-
-        // Check for WEBVTT header
-        if !first_line.starts_with("WEBVTT") {
-            return Err(VttParseError::MissingHeader);
-        }
-
-        let mut header = VttHeader::default();
-
-        // Parse description if present (everything after WEBVTT on the first line)
-        if first_line.len() > 6 {
-            header.description = Some(first_line[6..].trim().to_string());
-        }
-
-        // Parse metadata (key: value pairs before the first empty line)
-        for line in &mut lines {
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                break;
-            }
-
-            if let Some((key, value)) = trimmed.split_once(':') {
-                header
-                    .metadata
-                    .insert(key.trim().to_string(), value.trim().to_string());
-            } else {
-                return Err(VttParseError::InvalidMetadataLine(trimmed.to_string()));
-            }
-        }
-
-        // Parse cues
-        let mut cues = Vec::new();
-        let mut cue_lines = Vec::new();
-
-        for line in lines {
-            if line.trim().is_empty() {
-                if !cue_lines.is_empty() {
-                    cues.push(VttCue::from_str(&cue_lines.join("\n"))?);
-                    cue_lines.clear();
-                }
-            } else {
-                cue_lines.push(line);
-            }
-        }
-
-        // Don't forget the last cue if file doesn't end with empty line
-        if !cue_lines.is_empty() {
-            cues.push(VttCue::from_str(&cue_lines.join("\n"))?);
-        }
-
-        Ok(WebVtt { header, cues })
+        WebVttRef::parse(s).map(WebVttRef::into_owned)
     }
 }
 
+/// Returns `true` if the line is the given block keyword, alone or followed by whitespace.
+fn is_block_keyword(line: &str, keyword: &str) -> bool {
+    line.strip_prefix(keyword)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
 impl fmt::Display for WebVtt {
     /// Formats the `WebVtt` instance as a string following the WebVTT file format.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -702,6 +767,11 @@ impl fmt::Display for WebVtt {
         // Empty line after header section
         writeln!(f)?;
 
+        // Write style blocks
+        for style in &self.styles {
+            writeln!(f, "STYLE\n{}\n", style.trim())?;
+        }
+
         // Write cues
         for (i, cue) in self.cues.iter().enumerate() {
             if i > 0 {
@@ -719,18 +789,55 @@ impl fmt::Display for WebVtt {
 ///
 /// The prelude includes commonly used types, allowing for easier imports.
 pub mod prelude {
+    #[cfg(feature = "std")]
+    pub use super::VttReadError;
     pub use super::{
-        AlignSetting, LineSetting, VerticalSetting, VttCue, VttHeader, VttParseError, VttSettings,
-        VttTimestamp, WebVtt,
+        AlignSetting, CueIndex, LineSetting, VerticalSetting, VttCue, VttHeader, VttParseError,
+        VttSettings, VttTimestamp, WebVtt,
     };
 }
 
+/// Fixtures shared by the test modules.
+#[cfg(test)]
+pub(crate) mod test_util {
+    use crate::{VttCue, VttTimestamp};
+    use alloc::string::ToString;
+    use core::time::Duration;
+
+    /// Returns the timestamp at the given milliseconds.
+    pub(crate) fn ts(millis: u64) -> VttTimestamp {
+        VttTimestamp::new(Duration::from_millis(millis))
+    }
+
+    /// Returns a cue without identifier or settings.
+    pub(crate) fn cue(start: u64, end: u64, payload: &str) -> VttCue {
+        VttCue {
+            identifier: None,
+            start: ts(start),
+            end: ts(end),
+            settings: None,
+            payload: payload.to_string(),
+        }
+    }
+
+    /// Returns a cue with an identifier and without settings.
+    pub(crate) fn cue_with_id(id: &str, start: u64, end: u64, payload: &str) -> VttCue {
+        VttCue {
+            identifier: Some(id.to_string()),
+            ..cue(start, end, payload)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
+    #[cfg(feature = "std")]
     use std::io::Cursor;
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_from_reader() {
         let data = b"WEBVTT
 
@@ -747,6 +854,7 @@ Second subtitle";
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_from_reader_with_invalid_data() {
         let data = b"INVALID HEADER
 
@@ -756,10 +864,50 @@ Hello, world!";
         let result = WebVtt::from_reader(reader);
         assert!(result.is_err());
         match result {
-            Err(VttParseError::MissingHeader) => (),
+            Err(VttReadError::Parse(VttParseError::MissingHeader)) => (),
             _ => panic!("Expected MissingHeader error"),
         }
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_from_reader_with_failing_reader() {
+        struct FailingReader;
+        impl std::io::Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "timed out",
+                ))
+            }
+        }
+
+        let error = WebVtt::from_reader(FailingReader).unwrap_err();
+        match error {
+            VttReadError::Io(ref e) => assert_eq!(e.kind(), std::io::ErrorKind::TimedOut),
+            _ => panic!("Expected Io error"),
+        }
+        assert_eq!(error.to_string(), "I/O error: timed out");
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn test_invalid_timing_errors() {
+        assert!(matches!(
+            WebVtt::from_str(""),
+            Err(VttParseError::EmptyInput)
+        ));
+        assert!(matches!(
+            WebVtt::from_str("WEBVTT\n\nid only"),
+            Err(VttParseError::MissingTiming)
+        ));
+        match WebVtt::from_str("WEBVTT\n\n00:01.000 --> 00:02.000 --> 00:03.000\nHi") {
+            Err(VttParseError::InvalidTiming(line)) => {
+                assert_eq!(line, "00:01.000 --> 00:02.000 --> 00:03.000")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
     #[test]
     fn test_parse_timestamp() {
         let timestamp = VttTimestamp::from_str("01:23:45.678").unwrap();
@@ -855,6 +1003,110 @@ First subtitle"#;
         assert_eq!(vtt.cues.len(), 1);
     }
 
+    #[test]
+    fn test_whitespace_lines_do_not_end_blocks() {
+        // Only empty lines separate blocks, as in the WebVTT parser algorithm.
+        // A line of spaces is part of the cue payload.
+        let vtt = WebVtt::from_str(
+            "WEBVTT\n\n00:01.000 --> 00:02.000\nOne\n  \t\nTwo\n\n00:03.000 --> 00:04.000\nThree",
+        )
+        .unwrap();
+        assert_eq!(vtt.cues.len(), 2);
+        assert_eq!(vtt.cues[0].payload, "One\n  \t\nTwo");
+        assert_eq!(vtt.cues[1].payload, "Three");
+    }
+
+    #[test]
+    fn test_parse_header_rules() {
+        let vtt = WebVtt::from_str("WEBVTT\tTabbed\nfree-form text\n\n00:01.000 --> 00:02.000\nHi")
+            .unwrap();
+        assert_eq!(vtt.header.description, Some("Tabbed".to_string()));
+        assert!(vtt.header.metadata.is_empty());
+        assert_eq!(vtt.cues.len(), 1);
+
+        // A timing line ends the header even without an empty line before it.
+        let vtt = WebVtt::from_str("WEBVTT\nKind: captions\n00:01.000 --> 00:02.000\nHi").unwrap();
+        assert_eq!(vtt.header.metadata.get("Kind").unwrap(), "captions");
+        assert_eq!(vtt.cues[0].payload, "Hi");
+
+        for signature in ["WEBVTTfoo", "WEBVTT-foo", " WEBVTT", "webvtt"] {
+            assert!(matches!(
+                WebVtt::from_str(signature),
+                Err(VttParseError::MissingHeader)
+            ));
+        }
+        assert_eq!(
+            WebVtt::from_str("WEBVTT ").unwrap().header.description,
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_style_and_note_blocks() {
+        let content = r#"WEBVTT
+
+STYLE
+::cue(.yellow) {
+  color: yellow;
+}
+
+NOTE This is a comment
+
+00:00:01.000 --> 00:00:02.000
+<c.yellow>Hello</c>"#;
+
+        let vtt = WebVtt::from_str(content).unwrap();
+        assert_eq!(vtt.styles, vec!["::cue(.yellow) {\n  color: yellow;\n}"]);
+        assert_eq!(vtt.cues.len(), 1);
+
+        let expected = r#"WEBVTT
+
+STYLE
+::cue(.yellow) {
+  color: yellow;
+}
+
+00:00:01.000 --> 00:00:02.000
+<c.yellow>Hello</c>"#;
+        assert_eq!(vtt.to_string(), expected);
+    }
+
+    #[test]
+    fn test_style_and_note_round_trip() {
+        let content = "WEBVTT
+
+STYLE
+::cue { color: red }
+
+NOTE
+A comment
+on two lines
+
+STYLE
+::cue(b) { color: blue }
+
+NOTES
+00:00:01.000 --> 00:00:02.000
+First
+
+STYLE
+00:00:03.000 --> 00:00:04.000
+Second";
+
+        let vtt = WebVtt::from_str(content).unwrap();
+        assert_eq!(
+            vtt.styles,
+            vec!["::cue { color: red }", "::cue(b) { color: blue }"]
+        );
+        let ids: Vec<_> = vtt.cues.iter().map(|c| c.identifier.as_deref()).collect();
+        assert_eq!(ids, vec![Some("NOTES"), Some("STYLE")]);
+
+        let written = vtt.to_string();
+        assert!(!written.contains("A comment"));
+        assert_eq!(WebVtt::from_str(&written).unwrap(), vtt);
+    }
+
+    #[cfg(feature = "serde")]
     #[test]
     fn test_serialize_deserialize() {
         let mut vtt = WebVtt::new();
@@ -918,6 +1170,7 @@ Second Line should serialize with a newline"#;
         assert_eq!(vtt.to_string(), expected);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
     fn test_vtt_settings_serde() {
         let settings = VttSettings {
@@ -932,6 +1185,7 @@ Second Line should serialize with a newline"#;
         assert_eq!(settings, deserialized);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
     fn test_vtt_cue_serde() {
         let cue = VttCue {
@@ -952,7 +1206,9 @@ Second Line should serialize with a newline"#;
         assert_eq!(cue, deserialized);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn test_vtt_header_serde() {
         let mut header = VttHeader::default();
         header.description = Some("Sample File".to_string());
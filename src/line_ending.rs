@@ -0,0 +1,177 @@
+//! Line endings on input and output.
+
+use crate::WebVtt;
+use alloc::string::{String, ToString};
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+
+/// The line terminator used when writing a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LineEnding {
+    /// A line feed, `\n`.
+    #[default]
+    Lf,
+    /// A carriage return and line feed, `\r\n`, as used on Windows.
+    CrLf,
+    /// A lone carriage return, `\r`.
+    Cr,
+}
+
+impl LineEnding {
+    /// Returns the characters of the line ending.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// Returns the line ending ending the first line of the text, if any.
+    pub fn detect(text: &str) -> Option<Self> {
+        let i = text.find(['\r', '\n'])?;
+        Some(match &text[i..] {
+            rest if rest.starts_with("\r\n") => LineEnding::CrLf,
+            rest if rest.starts_with('\r') => LineEnding::Cr,
+            _ => LineEnding::Lf,
+        })
+    }
+
+    /// Converts all line endings in the text to this one.
+    pub fn apply(&self, text: &str) -> String {
+        let mut converted = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    chars.next_if_eq(&'\n');
+                    converted.push_str(self.as_str());
+                }
+                '\n' => converted.push_str(self.as_str()),
+                c => converted.push(c),
+            }
+        }
+        converted
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineEnding::Lf => write!(f, "lf"),
+            LineEnding::CrLf => write!(f, "crlf"),
+            LineEnding::Cr => write!(f, "cr"),
+        }
+    }
+}
+
+/// The error returned when parsing a `LineEnding` from an unknown name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLineEndingError(String);
+
+impl fmt::Display for ParseLineEndingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown line ending: {}", self.0)
+    }
+}
+
+impl Error for ParseLineEndingError {}
+
+impl FromStr for LineEnding {
+    type Err = ParseLineEndingError;
+
+    /// Parses `lf`, `crlf` or `cr`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::CrLf),
+            "cr" => Ok(LineEnding::Cr),
+            _ => Err(ParseLineEndingError(s.to_string())),
+        }
+    }
+}
+
+impl WebVtt {
+    /// Formats the file like `Display`, with the given line ending.
+    pub fn to_string_with_line_ending(&self, line_ending: LineEnding) -> String {
+        match line_ending {
+            LineEnding::Lf => self.to_string(),
+            _ => line_ending.apply(&self.to_string()),
+        }
+    }
+}
+
+/// Splits text into lines at `\r\n`, `\r` or `\n`, as the WebVTT parser
+/// algorithm does. Like `str::lines`, a final line terminator doesn't start
+/// another line.
+pub(crate) fn lines(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let (line, next) = match rest.find(['\r', '\n']) {
+            Some(i) if rest[i..].starts_with("\r\n") => (&rest[..i], &rest[i + 2..]),
+            Some(i) => (&rest[..i], &rest[i + 1..]),
+            None => (rest, ""),
+        };
+        rest = next;
+        Some(line)
+    })
+}
+
+/// Removes a leading UTF-8 byte-order mark.
+pub(crate) fn strip_bom(text: &str) -> &str {
+    text.strip_prefix('\u{FEFF}').unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_lines() {
+        let split: Vec<&str> = lines("a\r\nb\rc\n\r\nd\r").collect();
+        assert_eq!(split, vec!["a", "b", "c", "", "d"]);
+        assert_eq!(LineEnding::detect("WEBVTT\r\n"), Some(LineEnding::CrLf));
+        assert_eq!(LineEnding::detect("WEBVTT\rx\n"), Some(LineEnding::Cr));
+        assert_eq!(LineEnding::detect("WEBVTT"), None);
+    }
+
+    #[test]
+    fn test_parse_bom_and_line_endings() {
+        let expected =
+            WebVtt::from_str("WEBVTT\n\n1\n00:00:01.000 --> 00:00:02.000\nOne\nTwo").unwrap();
+        for source in [
+            "\u{FEFF}WEBVTT\r\n\r\n1\r\n00:00:01.000 --> 00:00:02.000\r\nOne\r\nTwo\r\n",
+            "\u{FEFF}WEBVTT\r\r1\r00:00:01.000 --> 00:00:02.000\rOne\rTwo\r",
+        ] {
+            assert_eq!(WebVtt::from_str(source).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_to_string_with_line_ending() {
+        let vtt = WebVtt::from_str("WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nOne\r\nTwo").unwrap();
+        assert_eq!(
+            vtt.to_string_with_line_ending(LineEnding::CrLf),
+            "WEBVTT\r\n\r\n00:00:01.000 --> 00:00:02.000\r\nOne\r\nTwo"
+        );
+        assert_eq!(
+            vtt.to_string_with_line_ending(LineEnding::Cr),
+            "WEBVTT\r\r00:00:01.000 --> 00:00:02.000\rOne\rTwo"
+        );
+    }
+
+    #[test]
+    fn test_parse_line_ending() {
+        assert_eq!(LineEnding::from_str("CRLF").unwrap(), LineEnding::CrLf);
+        assert_eq!(
+            LineEnding::from_str("lfcr").unwrap_err().to_string(),
+            "unknown line ending: lfcr"
+        );
+    }
+}
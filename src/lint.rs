@@ -0,0 +1,366 @@
+//! Reading-speed, layout and timing checks for captions.
+
+use crate::WebVtt;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+
+/// The severity of a lint finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// A stylistic suggestion.
+    Info,
+    /// A likely problem that should be reviewed.
+    Warning,
+    /// A violation of the configured rules.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A configured limit together with the severity of exceeding it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LintLimit<T> {
+    /// The limit value.
+    pub value: T,
+    /// The severity reported when the limit is violated.
+    pub severity: Severity,
+}
+
+impl<T> LintLimit<T> {
+    /// Creates a new `LintLimit`.
+    pub fn new(value: T, severity: Severity) -> Self {
+        LintLimit { value, severity }
+    }
+}
+
+/// The rules checked by `WebVtt::lint`. A rule set to `None` is not checked.
+///
+/// The default configuration follows common broadcast guidelines for adult
+/// English content: 20 characters per second, 42 characters per line, two
+/// lines, cues between 5/6 of a second and 7 seconds, and two frames between cues.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    /// Maximum reading speed in characters per second.
+    pub max_chars_per_second: Option<LintLimit<f64>>,
+    /// Maximum reading speed in words per minute.
+    pub max_words_per_minute: Option<LintLimit<f64>>,
+    /// Maximum number of characters on a line.
+    pub max_line_length: Option<LintLimit<usize>>,
+    /// Maximum number of lines in a cue.
+    pub max_lines: Option<LintLimit<usize>>,
+    /// Minimum duration of a cue.
+    pub min_duration: Option<LintLimit<Duration>>,
+    /// Maximum duration of a cue.
+    pub max_duration: Option<LintLimit<Duration>>,
+    /// Minimum gap between the end of a cue and the start of the next one.
+    pub min_gap: Option<LintLimit<Duration>>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            max_chars_per_second: Some(LintLimit::new(20.0, Severity::Error)),
+            max_words_per_minute: Some(LintLimit::new(200.0, Severity::Warning)),
+            max_line_length: Some(LintLimit::new(42, Severity::Error)),
+            max_lines: Some(LintLimit::new(2, Severity::Error)),
+            min_duration: Some(LintLimit::new(Duration::from_millis(833), Severity::Error)),
+            max_duration: Some(LintLimit::new(Duration::from_secs(7), Severity::Warning)),
+            min_gap: Some(LintLimit::new(Duration::from_millis(83), Severity::Warning)),
+        }
+    }
+}
+
+/// A rule violation found by `WebVtt::lint`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintIssue {
+    /// The cue exceeds the reading speed in characters per second.
+    CharsPerSecond { actual: f64, limit: f64 },
+    /// The cue exceeds the reading speed in words per minute.
+    WordsPerMinute { actual: f64, limit: f64 },
+    /// A line of the cue, counting from zero, is too long.
+    LineTooLong {
+        line: usize,
+        length: usize,
+        limit: usize,
+    },
+    /// The cue has too many lines.
+    TooManyLines { lines: usize, limit: usize },
+    /// The cue is displayed too briefly.
+    TooShort { duration: Duration, limit: Duration },
+    /// The cue is displayed too long.
+    TooLong { duration: Duration, limit: Duration },
+    /// The next cue starts too soon after this one ends.
+    GapTooShort { gap: Duration, limit: Duration },
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::CharsPerSecond { actual, limit } => write!(
+                f,
+                "{:.1} characters per second exceeds {:.1}",
+                actual, limit
+            ),
+            LintIssue::WordsPerMinute { actual, limit } => {
+                write!(f, "{:.0} words per minute exceeds {:.0}", actual, limit)
+            }
+            LintIssue::LineTooLong {
+                line,
+                length,
+                limit,
+            } => write!(
+                f,
+                "line {} has {} characters, more than {}",
+                line + 1,
+                length,
+                limit
+            ),
+            LintIssue::TooManyLines { lines, limit } => {
+                write!(f, "{} lines, more than {}", lines, limit)
+            }
+            LintIssue::TooShort { duration, limit } => write!(
+                f,
+                "duration {}ms is shorter than {}ms",
+                duration.as_millis(),
+                limit.as_millis()
+            ),
+            LintIssue::TooLong { duration, limit } => write!(
+                f,
+                "duration {}ms is longer than {}ms",
+                duration.as_millis(),
+                limit.as_millis()
+            ),
+            LintIssue::GapTooShort { gap, limit } => write!(
+                f,
+                "gap of {}ms to the next cue is shorter than {}ms",
+                gap.as_millis(),
+                limit.as_millis()
+            ),
+        }
+    }
+}
+
+/// A lint finding for a single cue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    /// Index of the offending cue.
+    pub cue: usize,
+    /// Severity of the finding.
+    pub severity: Severity,
+    /// The rule that was violated.
+    pub issue: LintIssue,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cue {}: {}: {}", self.cue, self.severity, self.issue)
+    }
+}
+
+impl WebVtt {
+    /// Checks the cues against the given rules.
+    ///
+    /// Findings are ordered by cue index. Character counts use the payload text
+    /// without markup, excluding line breaks.
+    pub fn lint(&self, config: &LintConfig) -> Vec<LintFinding> {
+        let mut findings = Vec::new();
+        let mut report = |cue: usize, severity: Severity, issue: LintIssue| {
+            findings.push(LintFinding {
+                cue,
+                severity,
+                issue,
+            })
+        };
+
+        // The gap is measured to the next cue to start; overlaps are not gaps.
+        let mut order: Vec<usize> = (0..self.cues.len()).collect();
+        order.sort_by_key(|&i| self.cues[i].start);
+        let mut next_start = vec![None; self.cues.len()];
+        for pair in order.windows(2) {
+            next_start[pair[0]] = Some(self.cues[pair[1]].start);
+        }
+
+        for (i, cue) in self.cues.iter().enumerate() {
+            let text = cue.plain_text();
+            let lines: Vec<&str> = text.trim().lines().collect();
+            let chars: usize = lines.iter().map(|l| l.chars().count()).sum();
+            let words = text.split_whitespace().count();
+            let duration = cue.duration();
+            let seconds = duration.as_secs_f64();
+
+            if let Some(limit) = config.max_chars_per_second {
+                let actual = chars as f64 / seconds;
+                if seconds > 0.0 && actual > limit.value {
+                    let issue = LintIssue::CharsPerSecond {
+                        actual,
+                        limit: limit.value,
+                    };
+                    report(i, limit.severity, issue);
+                }
+            }
+
+            if let Some(limit) = config.max_words_per_minute {
+                let actual = words as f64 * 60.0 / seconds;
+                if seconds > 0.0 && actual > limit.value {
+                    let issue = LintIssue::WordsPerMinute {
+                        actual,
+                        limit: limit.value,
+                    };
+                    report(i, limit.severity, issue);
+                }
+            }
+
+            if let Some(limit) = config.max_line_length {
+                for (line, text) in lines.iter().enumerate() {
+                    let length = text.chars().count();
+                    if length > limit.value {
+                        let issue = LintIssue::LineTooLong {
+                            line,
+                            length,
+                            limit: limit.value,
+                        };
+                        report(i, limit.severity, issue);
+                    }
+                }
+            }
+
+            if let Some(limit) = config.max_lines {
+                if lines.len() > limit.value {
+                    let issue = LintIssue::TooManyLines {
+                        lines: lines.len(),
+                        limit: limit.value,
+                    };
+                    report(i, limit.severity, issue);
+                }
+            }
+
+            if let Some(limit) = config.min_duration {
+                if duration < limit.value {
+                    let issue = LintIssue::TooShort {
+                        duration,
+                        limit: limit.value,
+                    };
+                    report(i, limit.severity, issue);
+                }
+            }
+
+            if let Some(limit) = config.max_duration {
+                if duration > limit.value {
+                    let issue = LintIssue::TooLong {
+                        duration,
+                        limit: limit.value,
+                    };
+                    report(i, limit.severity, issue);
+                }
+            }
+
+            if let (Some(limit), Some(next)) = (config.min_gap, next_start[i]) {
+                if next >= cue.end {
+                    let gap = next.as_duration() - cue.end.as_duration();
+                    if gap < limit.value {
+                        let issue = LintIssue::GapTooShort {
+                            gap,
+                            limit: limit.value,
+                        };
+                        report(i, limit.severity, issue);
+                    }
+                }
+            }
+        }
+
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use core::str::FromStr;
+
+    #[test]
+    fn test_lint_clean_file() {
+        let vtt = WebVtt::from_str(
+            "WEBVTT
+
+00:00:01.000 --> 00:00:03.000
+Hello there.
+
+00:00:04.000 --> 00:00:06.000
+<i>How are you?</i>",
+        )
+        .unwrap();
+        assert!(vtt.lint(&LintConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_lint_findings() {
+        let vtt = WebVtt::from_str(
+            "WEBVTT
+
+00:00:01.000 --> 00:00:01.500
+This line is far too long to be read comfortably on screen
+and it has
+three lines
+
+00:00:01.510 --> 00:00:10.000
+Slow",
+        )
+        .unwrap();
+        let findings = vtt.lint(&LintConfig::default());
+        let issues: Vec<(usize, &LintIssue)> = findings.iter().map(|f| (f.cue, &f.issue)).collect();
+
+        assert!(matches!(issues[0], (0, LintIssue::CharsPerSecond { .. })));
+        assert!(matches!(issues[1], (0, LintIssue::WordsPerMinute { .. })));
+        assert!(matches!(
+            issues[2],
+            (
+                0,
+                LintIssue::LineTooLong {
+                    line: 0,
+                    length: 58,
+                    limit: 42
+                }
+            )
+        ));
+        assert!(matches!(
+            issues[3],
+            (0, LintIssue::TooManyLines { lines: 3, limit: 2 })
+        ));
+        assert!(matches!(issues[4], (0, LintIssue::TooShort { .. })));
+        assert!(matches!(issues[5], (0, LintIssue::GapTooShort { .. })));
+        assert!(matches!(issues[6], (1, LintIssue::TooLong { .. })));
+        assert_eq!(issues.len(), 7);
+        assert_eq!(
+            findings[3].to_string(),
+            "cue 0: error: 3 lines, more than 2"
+        );
+    }
+
+    #[test]
+    fn test_lint_disabled_rules() {
+        let vtt = WebVtt::from_str("WEBVTT\n\n00:00:00.000 --> 00:00:00.100\nHello").unwrap();
+        let config = LintConfig {
+            min_duration: None,
+            max_words_per_minute: None,
+            ..LintConfig::default()
+        };
+        let findings = vtt.lint(&config);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert!(matches!(
+            findings[0].issue,
+            LintIssue::CharsPerSecond { .. }
+        ));
+    }
+}
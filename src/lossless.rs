@@ -0,0 +1,414 @@
+//! Lossless round-trips of WebVTT files.
+
+use crate::borrowed::offset;
+use crate::diff::match_cues;
+use crate::line_ending::{lines, LineEnding};
+use crate::{is_block_keyword, VttCue, VttParseError, WebVtt};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Deref, DerefMut, Range};
+use core::str::FromStr;
+
+/// The kind of a block of the source text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockKind {
+    Header,
+    Style,
+    Note,
+    Cue,
+}
+
+/// A block of the source text, with the blank lines and other text between
+/// it and the previous block.
+#[derive(Debug, Clone, PartialEq)]
+struct Block {
+    kind: BlockKind,
+    leading: Range<usize>,
+    text: Range<usize>,
+}
+
+/// How far `Display for LosslessWebVtt` has got through the source blocks.
+struct Progress {
+    /// The first source block not yet passed.
+    next: usize,
+    /// Whether changed style blocks still need to be written.
+    styles_pending: bool,
+}
+
+/// A WebVTT file that keeps its source text, so it can be written back
+/// without normalizing it.
+///
+/// The parsed file is available through `Deref` and `DerefMut`. When
+/// formatted, the header, style blocks and cues that are unchanged are written
+/// exactly as they were read, including comments, blank lines, line endings
+/// and the spelling of timestamps and settings. Only changed and added cues,
+/// and a changed header or changed style blocks, are written in the
+/// normalized form of `Display for WebVtt`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LosslessWebVtt {
+    vtt: WebVtt,
+    original: WebVtt,
+    source: String,
+    blocks: Vec<Block>,
+    /// The index in `blocks` of each cue of `original`.
+    cue_blocks: Vec<usize>,
+    line_ending: LineEnding,
+}
+
+impl LosslessWebVtt {
+    /// Parses a WebVTT file, keeping its source text.
+    pub fn parse(s: &str) -> Result<Self, VttParseError> {
+        let vtt = WebVtt::from_str(s)?;
+
+        // Find the blocks the same way `WebVtt::from_str` does.
+        let mut lines = lines(s).peekable();
+        let first_line = lines.next().ok_or(VttParseError::EmptyInput)?;
+        let mut header_end = offset(s, first_line) + first_line.len();
+        while let Some(line) = lines.next_if(|line| !line.contains("-->")) {
+            if line.is_empty() {
+                break;
+            }
+            header_end = offset(s, line) + line.len();
+        }
+        let mut blocks = vec![Block {
+            kind: BlockKind::Header,
+            leading: 0..0,
+            text: 0..header_end,
+        }];
+
+        let mut previous_end = header_end;
+        let mut block: Option<(Range<usize>, &str, bool)> = None;
+        let mut cue_blocks = Vec::new();
+        let lines = lines.map(Some).chain([None]);
+        for line in lines {
+            match line {
+                Some(line) if !line.is_empty() => {
+                    let start = offset(s, line);
+                    let end = start + line.len();
+                    match block {
+                        Some((ref mut range, _, ref mut timing)) => {
+                            range.end = end;
+                            *timing |= line.contains("-->");
+                        }
+                        None => block = Some((start..end, line, line.contains("-->"))),
+                    }
+                }
+                _ => {
+                    let Some((text, first, timing)) = block.take() else {
+                        continue;
+                    };
+                    let kind = if is_block_keyword(first, "NOTE") {
+                        BlockKind::Note
+                    } else if is_block_keyword(first, "STYLE") && cue_blocks.is_empty() && !timing {
+                        BlockKind::Style
+                    } else {
+                        cue_blocks.push(blocks.len());
+                        BlockKind::Cue
+                    };
+                    blocks.push(Block {
+                        kind,
+                        leading: previous_end..text.start,
+                        text: text.clone(),
+                    });
+                    previous_end = text.end;
+                }
+            }
+        }
+
+        let line_ending = LineEnding::detect(s).unwrap_or_default();
+        Ok(LosslessWebVtt {
+            original: vtt.clone(),
+            vtt,
+            source: s.to_string(),
+            blocks,
+            cue_blocks,
+            line_ending,
+        })
+    }
+
+    /// Returns the source text the file was parsed from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the parsed file, dropping the source text.
+    pub fn into_inner(self) -> WebVtt {
+        self.vtt
+    }
+
+    /// Converts normalized text to the line endings of the source.
+    fn lines(&self, text: &str) -> String {
+        self.line_ending.apply(text)
+    }
+
+    fn write_header(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.vtt.header == self.original.header {
+            return f.write_str(&self.source[self.blocks[0].text.clone()]);
+        }
+        match self.vtt.header.description {
+            Some(ref description) => write!(f, "WEBVTT {}", description)?,
+            None => write!(f, "WEBVTT")?,
+        }
+        for (key, value) in &self.vtt.header.metadata {
+            write!(f, "{}{}: {}", self.line_ending.as_str(), key, value)?;
+        }
+        Ok(())
+    }
+
+    fn write_styles(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for style in &self.vtt.styles {
+            let block = self.lines(&format!("\n\nSTYLE\n{}", style.trim()));
+            f.write_str(&block)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the comments and style blocks before the block at `until`.
+    ///
+    /// They are written in their original place relative to the cues around
+    /// them. Cue blocks passed over belong to deleted cues, or to cues already
+    /// written out of order.
+    fn flush(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        progress: &mut Progress,
+        until: usize,
+    ) -> fmt::Result {
+        let styles_changed = self.vtt.styles != self.original.styles;
+        for block in self.blocks.get(progress.next..until).unwrap_or_default() {
+            let unchanged = match block.kind {
+                BlockKind::Note => true,
+                BlockKind::Style => !styles_changed,
+                _ => false,
+            };
+            if unchanged {
+                f.write_str(&self.source[block.leading.clone()])?;
+                f.write_str(&self.source[block.text.clone()])?;
+            } else if block.kind == BlockKind::Style && progress.styles_pending {
+                self.write_styles(f)?;
+                progress.styles_pending = false;
+            }
+        }
+        progress.next = progress.next.max(until);
+        Ok(())
+    }
+}
+
+impl fmt::Display for LosslessWebVtt {
+    /// Writes the file, keeping the source text of everything unchanged.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Cues left in place are paired directly, and only the others are
+        // matched to the remaining original cues.
+        let mut original_cue = vec![None; self.vtt.cues.len()];
+        let mut paired = vec![false; self.original.cues.len()];
+        for (j, cue) in self.vtt.cues.iter().enumerate() {
+            if self.original.cues.get(j) == Some(cue) {
+                original_cue[j] = Some(j);
+                paired[j] = true;
+            }
+        }
+        let old: Vec<usize> = (0..paired.len()).filter(|&i| !paired[i]).collect();
+        let new: Vec<usize> = (0..original_cue.len())
+            .filter(|&j| original_cue[j].is_none())
+            .collect();
+        if !old.is_empty() && !new.is_empty() {
+            let old_cues: Vec<VttCue> =
+                old.iter().map(|&i| self.original.cues[i].clone()).collect();
+            let new_cues: Vec<VttCue> = new.iter().map(|&j| self.vtt.cues[j].clone()).collect();
+            for (i, j) in match_cues(&old_cues, &new_cues) {
+                original_cue[new[j]] = Some(old[i]);
+            }
+        }
+        let first_cue = self
+            .cue_blocks
+            .first()
+            .copied()
+            .unwrap_or(self.blocks.len());
+
+        self.write_header(f)?;
+        let mut progress = Progress {
+            next: 1,
+            styles_pending: self.vtt.styles != self.original.styles,
+        };
+
+        for (j, cue) in self.vtt.cues.iter().enumerate() {
+            let original = original_cue[j].map(|i| (i, self.cue_blocks[i]));
+            match original {
+                Some((i, k)) => {
+                    self.flush(f, &mut progress, k)?;
+                    if progress.styles_pending {
+                        self.write_styles(f)?;
+                        progress.styles_pending = false;
+                    }
+                    progress.next = progress.next.max(k + 1);
+                    let block = &self.blocks[k];
+                    f.write_str(&self.source[block.leading.clone()])?;
+                    if *cue == self.original.cues[i] {
+                        f.write_str(&self.source[block.text.clone()])?;
+                    } else {
+                        f.write_str(&self.lines(&cue.to_string()))?;
+                    }
+                }
+                None => {
+                    // Added cues are written after the comments preceding the
+                    // first cue, or after the cue they follow.
+                    self.flush(f, &mut progress, first_cue)?;
+                    if progress.styles_pending {
+                        self.write_styles(f)?;
+                        progress.styles_pending = false;
+                    }
+                    f.write_str(&self.lines(&format!("\n\n{}", cue)))?;
+                }
+            }
+        }
+
+        self.flush(f, &mut progress, self.blocks.len())?;
+        if progress.styles_pending {
+            self.write_styles(f)?;
+        }
+        let end = self.blocks.last().map_or(0, |block| block.text.end);
+        f.write_str(&self.source[end..])
+    }
+}
+
+impl FromStr for LosslessWebVtt {
+    type Err = VttParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Deref for LosslessWebVtt {
+    type Target = WebVtt;
+
+    fn deref(&self) -> &WebVtt {
+        &self.vtt
+    }
+}
+
+impl DerefMut for LosslessWebVtt {
+    fn deref_mut(&mut self) -> &mut WebVtt {
+        &mut self.vtt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{VttCue, VttTimestamp};
+    use core::time::Duration;
+
+    const SOURCE: &str = "WEBVTT  Example\r
+Kind: captions\r
+\r
+\r
+NOTE keep me\r
+\r
+1\r
+00:01.5 --> 00:00:03.000   align:start line:10%\r
+First  \r
+\r
+2\r
+00:00:04.000 --> 00:00:05.000\r
+Second\r
+\r
+3\r
+00:00:06.000 --> 00:00:07.000\r
+Third\r
+";
+
+    #[test]
+    fn test_unchanged_round_trip() {
+        let vtt = LosslessWebVtt::parse(SOURCE).unwrap();
+        assert_eq!(vtt.to_string(), SOURCE);
+        assert_eq!(vtt.cues.len(), 3);
+        assert_ne!(vtt.clone().into_inner().to_string(), SOURCE);
+    }
+
+    #[test]
+    fn test_edits_touch_only_changed_cues() {
+        let mut vtt = LosslessWebVtt::parse(SOURCE).unwrap();
+        vtt.cues[1].payload = "Second, edited".to_string();
+        vtt.cues.remove(2);
+        vtt.cues.push(VttCue {
+            identifier: Some("4".to_string()),
+            start: VttTimestamp::new(Duration::from_secs(8)),
+            end: VttTimestamp::new(Duration::from_secs(9)),
+            settings: None,
+            payload: "Fourth".to_string(),
+        });
+        assert_eq!(
+            vtt.to_string(),
+            "WEBVTT  Example\r
+Kind: captions\r
+\r
+\r
+NOTE keep me\r
+\r
+1\r
+00:01.5 --> 00:00:03.000   align:start line:10%\r
+First  \r
+\r
+2\r
+00:00:04.000 --> 00:00:05.000\r
+Second, edited\r
+\r
+4\r
+00:00:08.000 --> 00:00:09.000\r
+Fourth\r
+"
+        );
+    }
+
+    #[test]
+    fn test_header_ended_by_timing_line() {
+        let source = "WEBVTT\nnot metadata\n00:01.000 --> 00:02.000\nHi\n";
+        let vtt = LosslessWebVtt::parse(source).unwrap();
+        assert_eq!(vtt.to_string(), source);
+        assert!(vtt.header.metadata.is_empty());
+        assert_eq!(vtt.cues[0].payload, "Hi");
+    }
+
+    #[test]
+    fn test_changed_header_and_styles() {
+        let mut vtt = LosslessWebVtt::parse("WEBVTT\n\n00:01.000 --> 00:02.000\nHi\n").unwrap();
+        vtt.add_metadata("Language", "en");
+        vtt.styles.push("::cue { color: yellow }".to_string());
+        assert_eq!(
+            vtt.to_string(),
+            "WEBVTT\nLanguage: en\n\nSTYLE\n::cue { color: yellow }\n\n00:01.000 --> 00:02.000\nHi\n"
+        );
+    }
+
+    #[test]
+    fn test_large_file_with_inserted_cue() {
+        let mut source = String::from("WEBVTT\n");
+        for i in 0..3000 {
+            source.push_str(&format!(
+                "\n{}:00.000 --> {}:01.000\nLine {}\n",
+                i,
+                i,
+                i % 7
+            ));
+        }
+        let mut vtt = LosslessWebVtt::parse(&source).unwrap();
+        assert_eq!(vtt.to_string(), source);
+
+        // Cues after the inserted one moved to the next index but keep their
+        // source text.
+        vtt.cues
+            .insert(0, crate::test_util::cue(0, 500, "Before everything"));
+        assert_eq!(
+            vtt.to_string(),
+            source.replacen(
+                "WEBVTT\n",
+                "WEBVTT\n\n00:00:00.000 --> 00:00:00.500\nBefore everything\n",
+                1
+            )
+        );
+    }
+}
@@ -0,0 +1,518 @@
+//! The `vtt` command-line tool.
+//!
+//! Exit codes: 0 on success, 1 when the input is invalid or checks fail, 2 on
+//! usage errors and 3 on I/O errors.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Duration;
+use vtt::{
+    Encoding, LineEnding, LintConfig, LintLimit, Severity, Structured, TranscriptConfig,
+    VttTimestamp, WebVtt,
+};
+
+const USAGE: &str = "\
+Usage: vtt <command> [options] [file]
+
+Reads from standard input when no file or `-` is given, and writes to
+standard output unless `-o <file>` is given.
+
+Commands:
+  validate [file]                 Check that the input parses
+  fmt [file]                      Rewrite the input in canonical form
+  convert --to <format> [file]    Convert to vtt, txt, json, xliff or po
+  shift --by <offset> [file]      Move all cues, e.g. --by 1.5s or --by -250ms
+  lint [file]                     Check reading speed, layout and timing
+  stats [file]                    Show cue, timing and speaker statistics
+  merge <base> <ours> <theirs>    Three-way merge of concurrent edits
+
+Options:
+  -o, --output <file>             Write output to a file
+  --json                          Print results as JSON
+  --line-ending <lf|crlf|cr>      Line ending of written WebVTT files
+  --encoding <name>               Encoding of input that isn't UTF-8 or UTF-16,
+                                  e.g. windows-1252 or latin1
+  --timestamps, --speakers        Prefixes for `convert --to txt`
+  --source-lang, --target-lang    Languages for `convert --to xliff|po`
+  --max-cps <n>, --max-line-length <n>, --max-lines <n>
+                                  Limits for `lint`
+  --strict                        Make `lint` fail on warnings too
+  -h, --help                      Show this help
+";
+
+/// Options that don't take a value.
+const FLAGS: &[&str] = &["json", "strict", "timestamps", "speakers", "help"];
+
+/// Options that take a value.
+const OPTIONS: &[&str] = &[
+    "output",
+    "to",
+    "by",
+    "line-ending",
+    "encoding",
+    "source-lang",
+    "target-lang",
+    "max-cps",
+    "max-line-length",
+    "max-lines",
+];
+
+/// An error ending the command, with the exit code it maps to.
+#[derive(Debug)]
+enum CliError {
+    /// The input is invalid or a check failed.
+    Failed(String),
+    /// The command line is invalid.
+    Usage(String),
+    /// Reading or writing failed.
+    Io(String, io::Error),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Failed(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::Io(..) => 3,
+        }
+    }
+}
+
+/// Parsed command-line arguments.
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, CliError> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let name = match arg.as_str() {
+                "-o" => "output".to_string(),
+                "-h" => "help".to_string(),
+                "-" => {
+                    parsed.positional.push(arg);
+                    continue;
+                }
+                _ => match arg.strip_prefix("--") {
+                    Some(name) => name.to_string(),
+                    None if arg.starts_with('-') => {
+                        return Err(CliError::Usage(format!("unknown option: {}", arg)));
+                    }
+                    None => {
+                        parsed.positional.push(arg);
+                        continue;
+                    }
+                },
+            };
+
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (name, None),
+            };
+            let value = if FLAGS.contains(&name.as_str()) {
+                value.unwrap_or_default()
+            } else if OPTIONS.contains(&name.as_str()) {
+                match value.or_else(|| args.next()) {
+                    Some(value) => value,
+                    None => return Err(CliError::Usage(format!("missing value for --{}", name))),
+                }
+            } else {
+                return Err(CliError::Usage(format!("unknown option: --{}", name)));
+            };
+            parsed.options.insert(name, value);
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn number<T: FromStr>(&self, name: &str) -> Result<Option<T>, CliError> {
+        self.option(name)
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| CliError::Usage(format!("invalid value for --{}: {}", name, v)))
+            })
+            .transpose()
+    }
+
+    /// Returns the single input path, or `-` for standard input.
+    fn input(&self) -> Result<&str, CliError> {
+        match self.positional.as_slice() {
+            [] => Ok("-"),
+            [path] => Ok(path),
+            _ => Err(CliError::Usage("too many arguments".to_string())),
+        }
+    }
+}
+
+fn read_input(path: &str) -> Result<Vec<u8>, CliError> {
+    let result = if path == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+    } else {
+        fs::read(path)
+    };
+    result.map_err(|e| CliError::Io(path.to_string(), e))
+}
+
+/// Returns the encoding given by `--encoding`, used for input that isn't
+/// UTF-8 or UTF-16.
+fn fallback_encoding(args: &Args) -> Result<Option<Encoding>, CliError> {
+    args.option("encoding")
+        .map(|name| Encoding::from_str(name).map_err(|e| CliError::Usage(e.to_string())))
+        .transpose()
+}
+
+fn parse_input(args: &Args, path: &str) -> Result<WebVtt, CliError> {
+    let fallback = fallback_encoding(args)?;
+    WebVtt::from_bytes(&read_input(path)?, fallback)
+        .map_err(|e| CliError::Failed(format!("{}: {}", path, e)))
+}
+
+fn write_output(args: &Args, text: &str) -> Result<(), CliError> {
+    match args.option("output") {
+        Some(path) if path != "-" => {
+            fs::write(path, text).map_err(|e| CliError::Io(path.to_string(), e))
+        }
+        _ => io::stdout()
+            .write_all(text.as_bytes())
+            .map_err(|e| CliError::Io("stdout".to_string(), e)),
+    }
+}
+
+/// Formats a file for output, with the line ending given by `--line-ending`.
+fn vtt_output(args: &Args, vtt: &WebVtt) -> Result<String, CliError> {
+    let line_ending = match args.option("line-ending") {
+        Some(name) => LineEnding::from_str(name).map_err(|e| CliError::Usage(e.to_string()))?,
+        None => LineEnding::default(),
+    };
+    Ok(line_ending.apply(&format!("{}\n", vtt)))
+}
+
+fn print_json(args: &Args, value: &Value) -> Result<(), CliError> {
+    write_output(args, &format!("{:#}\n", value))
+}
+
+/// Parses a time offset such as `1.5s`, `-250ms` or `+00:01:02.500`.
+fn parse_offset(text: &str) -> Option<(bool, Duration)> {
+    let (earlier, rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let duration = if rest.contains(':') {
+        VttTimestamp::from_str(rest).ok()?.as_duration()
+    } else if let Some(millis) = rest.strip_suffix("ms") {
+        Duration::from_millis(millis.parse().ok()?)
+    } else {
+        let secs: f64 = rest.strip_suffix('s').unwrap_or(rest).parse().ok()?;
+        Duration::try_from_secs_f64(secs).ok()?
+    };
+    Some((earlier, duration))
+}
+
+fn validate(args: &Args) -> Result<(), CliError> {
+    let path = args.input()?;
+    let fallback = fallback_encoding(args)?;
+    let result = WebVtt::from_bytes(&read_input(path)?, fallback);
+    if args.flag("json") {
+        let value = match result {
+            Ok(ref vtt) => json!({ "valid": true, "cues": vtt.cues.len() }),
+            Err(ref e) => json!({ "valid": false, "error": e.to_string() }),
+        };
+        print_json(args, &value)?;
+    }
+    match result {
+        Ok(vtt) if !args.flag("json") => {
+            write_output(args, &format!("{}: valid, {} cues\n", path, vtt.cues.len()))
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(CliError::Failed(format!("{}: {}", path, e))),
+    }
+}
+
+fn fmt(args: &Args) -> Result<(), CliError> {
+    let vtt = parse_input(args, args.input()?)?;
+    write_output(args, &vtt_output(args, &vtt)?)
+}
+
+fn convert(args: &Args) -> Result<(), CliError> {
+    let vtt = parse_input(args, args.input()?)?;
+    let source_lang = args.option("source-lang").unwrap_or("en");
+    let target_lang = args.option("target-lang").unwrap_or("und");
+    let output = match args.option("to") {
+        Some("vtt") => vtt_output(args, &vtt)?,
+        Some("txt") => vtt.to_transcript(&TranscriptConfig {
+            timestamps: args.flag("timestamps"),
+            speakers: args.flag("speakers"),
+            ..TranscriptConfig::default()
+        }),
+        Some("json") => serde_json::to_string_pretty(&Structured::new(&vtt))
+            .map(|json| json + "\n")
+            .map_err(|e| CliError::Failed(e.to_string()))?,
+        Some("xliff") => vtt.to_xliff(source_lang, target_lang),
+        Some("po") => vtt.to_po(target_lang),
+        Some(format) => return Err(CliError::Usage(format!("unknown format: {}", format))),
+        None => return Err(CliError::Usage("missing --to <format>".to_string())),
+    };
+    write_output(args, &output)
+}
+
+fn shift(args: &Args) -> Result<(), CliError> {
+    let by = args
+        .option("by")
+        .ok_or_else(|| CliError::Usage("missing --by <offset>".to_string()))?;
+    let (earlier, offset) =
+        parse_offset(by).ok_or_else(|| CliError::Usage(format!("invalid offset: {}", by)))?;
+    let mut vtt = parse_input(args, args.input()?)?;
+    if earlier {
+        vtt.shift_earlier(offset);
+    } else {
+        vtt.shift_later(offset);
+    }
+    write_output(args, &vtt_output(args, &vtt)?)
+}
+
+fn lint(args: &Args) -> Result<(), CliError> {
+    let vtt = parse_input(args, args.input()?)?;
+    let mut config = LintConfig::default();
+    if let Some(value) = args.number("max-cps")? {
+        config.max_chars_per_second = Some(LintLimit::new(value, Severity::Error));
+    }
+    if let Some(value) = args.number("max-line-length")? {
+        config.max_line_length = Some(LintLimit::new(value, Severity::Error));
+    }
+    if let Some(value) = args.number("max-lines")? {
+        config.max_lines = Some(LintLimit::new(value, Severity::Error));
+    }
+
+    let findings = vtt.lint(&config);
+    if args.flag("json") {
+        let findings: Vec<Value> = findings
+            .iter()
+            .map(|f| {
+                json!({
+                    "cue": f.cue,
+                    "start": vtt.cues[f.cue].start.to_string(),
+                    "severity": f.severity.to_string(),
+                    "message": f.issue.to_string(),
+                })
+            })
+            .collect();
+        print_json(args, &Value::Array(findings))?;
+    } else {
+        let text: String = findings.iter().map(|f| format!("{}\n", f)).collect();
+        write_output(args, &text)?;
+    }
+
+    let threshold = if args.flag("strict") {
+        Severity::Warning
+    } else {
+        Severity::Error
+    };
+    let failed = findings.iter().filter(|f| f.severity >= threshold).count();
+    if failed > 0 {
+        return Err(CliError::Failed(format!("{} problems found", failed)));
+    }
+    Ok(())
+}
+
+fn stats(args: &Args) -> Result<(), CliError> {
+    let vtt = parse_input(args, args.input()?)?;
+    let duration = vtt.cues.iter().map(|c| c.end).max().unwrap_or_default();
+    let shown: Duration = vtt.cues.iter().map(|c| c.duration()).sum();
+    let text: Vec<String> = vtt.cues.iter().map(|c| c.plain_text()).collect();
+    let words: usize = text.iter().map(|t| t.split_whitespace().count()).sum();
+    let chars: usize = text
+        .iter()
+        .map(|t| t.chars().filter(|c| *c != '\n').count())
+        .sum();
+    let cps = if shown.is_zero() {
+        0.0
+    } else {
+        chars as f64 / shown.as_secs_f64()
+    };
+    let speakers = vtt.speakers();
+
+    if args.flag("json") {
+        let speakers: Vec<Value> = speakers
+            .iter()
+            .map(|s| {
+                json!({
+                    "name": s.name,
+                    "cues": s.cues,
+                    "talk_time": VttTimestamp::new(s.talk_time).to_string(),
+                })
+            })
+            .collect();
+        return print_json(
+            args,
+            &json!({
+                "cues": vtt.cues.len(),
+                "duration": duration.to_string(),
+                "words": words,
+                "characters": chars,
+                "chars_per_second": (cps * 10.0).round() / 10.0,
+                "speakers": speakers,
+            }),
+        );
+    }
+
+    let mut output = format!(
+        "cues: {}\nduration: {}\nwords: {}\ncharacters: {}\nchars per second: {:.1}\n",
+        vtt.cues.len(),
+        duration,
+        words,
+        chars,
+        cps
+    );
+    for s in &speakers {
+        output.push_str(&format!(
+            "speaker {}: {} cues, {}\n",
+            s.name,
+            s.cues,
+            VttTimestamp::new(s.talk_time)
+        ));
+    }
+    write_output(args, &output)
+}
+
+fn merge(args: &Args) -> Result<(), CliError> {
+    let [base, ours, theirs] = args.positional.as_slice() else {
+        return Err(CliError::Usage(
+            "merge needs <base> <ours> <theirs>".to_string(),
+        ));
+    };
+    let base = parse_input(args, base)?;
+    let result = base.merge3(&parse_input(args, ours)?, &parse_input(args, theirs)?);
+
+    let conflicts: Vec<String> = result
+        .conflicts
+        .iter()
+        .map(|c| {
+            let at = [&c.ours, &c.theirs, &c.base]
+                .into_iter()
+                .flatten()
+                .map(|cue| format!(" at {}", cue.start))
+                .next()
+                .unwrap_or_default();
+            format!("{:?}{}", c.kind, at)
+        })
+        .collect();
+
+    if args.flag("json") {
+        print_json(
+            args,
+            &json!({
+                "merged": vtt_output(args, &result.merged)?,
+                "conflicts": conflicts,
+            }),
+        )?;
+    } else {
+        write_output(args, &vtt_output(args, &result.merged)?)?;
+    }
+
+    if !conflicts.is_empty() {
+        return Err(CliError::Failed(format!(
+            "{} conflicts: {}",
+            conflicts.len(),
+            conflicts.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+fn run() -> Result<(), CliError> {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let args = Args::parse(args)?;
+
+    if args.flag("help") || command == "help" || command == "-h" || command == "--help" {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+
+    match command.as_str() {
+        "validate" => validate(&args),
+        "fmt" => fmt(&args),
+        "convert" => convert(&args),
+        "shift" => shift(&args),
+        "lint" => lint(&args),
+        "stats" => stats(&args),
+        "merge" => merge(&args),
+        "" => Err(CliError::Usage("missing command".to_string())),
+        _ => Err(CliError::Usage(format!("unknown command: {}", command))),
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            match error {
+                CliError::Failed(ref message) => eprintln!("vtt: {}", message),
+                CliError::Usage(ref message) => eprintln!("vtt: {}\n\n{}", message, USAGE),
+                CliError::Io(ref path, ref e) => eprintln!("vtt: {}: {}", path, e),
+            }
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let args = [
+            "--by", "-1s", "in.vtt", "--json", "-o", "out.vtt", "--to=po",
+        ]
+        .into_iter()
+        .map(str::to_string);
+        let args = Args::parse(args).unwrap();
+        assert_eq!(args.positional, vec!["in.vtt"]);
+        assert_eq!(args.option("by"), Some("-1s"));
+        assert_eq!(args.option("output"), Some("out.vtt"));
+        assert_eq!(args.option("to"), Some("po"));
+        assert!(args.flag("json"));
+        assert!(Args::parse(vec!["--by".to_string()]).is_err());
+
+        // Unknown options are rejected instead of taking the next argument.
+        for unknown in ["--bogus", "--bogus=1", "-x"] {
+            let args = [unknown, "in.vtt"].into_iter().map(str::to_string);
+            assert!(matches!(Args::parse(args), Err(CliError::Usage(_))));
+        }
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(
+            parse_offset("1.5s"),
+            Some((false, Duration::from_millis(1500)))
+        );
+        assert_eq!(
+            parse_offset("-250ms"),
+            Some((true, Duration::from_millis(250)))
+        );
+        assert_eq!(
+            parse_offset("+01:02.003"),
+            Some((false, Duration::from_millis(62_003)))
+        );
+        assert_eq!(parse_offset("2"), Some((false, Duration::from_secs(2))));
+        assert_eq!(parse_offset("soon"), None);
+    }
+}
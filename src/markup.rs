@@ -0,0 +1,489 @@
+//! Parsing of WebVTT cue text markup.
+
+use crate::VttTimestamp;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+/// A node of parsed cue text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CueNode {
+    /// Text, with character references decoded.
+    Text(String),
+    /// A timestamp tag, marking when the following text becomes active.
+    Timestamp(VttTimestamp),
+    /// A span of text enclosed in a tag.
+    Span(CueSpan),
+}
+
+/// A span of cue text enclosed in a tag such as `<v.loud Bob>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueSpan {
+    /// The kind of tag.
+    pub kind: SpanKind,
+    /// The classes given after the tag name, e.g. `loud`.
+    pub classes: Vec<String>,
+    /// The annotation after the tag name: the speaker of a voice span or the
+    /// language of a language span.
+    pub annotation: Option<String>,
+    /// The content of the span.
+    pub children: Vec<CueNode>,
+}
+
+/// The kinds of spans in cue text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpanKind {
+    /// A class span, `<c>`.
+    Class,
+    /// An italics span, `<i>`.
+    Italic,
+    /// A bold span, `<b>`.
+    Bold,
+    /// An underline span, `<u>`.
+    Underline,
+    /// A ruby span, `<ruby>`.
+    Ruby,
+    /// A ruby text span, `<rt>`.
+    RubyText,
+    /// A voice span, `<v>`.
+    Voice,
+    /// A language span, `<lang>`.
+    Language,
+}
+
+impl SpanKind {
+    /// Returns the tag name of the span kind.
+    pub fn tag_name(&self) -> &'static str {
+        match self {
+            SpanKind::Class => "c",
+            SpanKind::Italic => "i",
+            SpanKind::Bold => "b",
+            SpanKind::Underline => "u",
+            SpanKind::Ruby => "ruby",
+            SpanKind::RubyText => "rt",
+            SpanKind::Voice => "v",
+            SpanKind::Language => "lang",
+        }
+    }
+
+    pub(crate) fn from_tag_name(name: &str) -> Option<Self> {
+        match name {
+            "c" => Some(SpanKind::Class),
+            "i" => Some(SpanKind::Italic),
+            "b" => Some(SpanKind::Bold),
+            "u" => Some(SpanKind::Underline),
+            "ruby" => Some(SpanKind::Ruby),
+            "rt" => Some(SpanKind::RubyText),
+            "v" => Some(SpanKind::Voice),
+            "lang" => Some(SpanKind::Language),
+            _ => None,
+        }
+    }
+}
+
+/// Parses cue text into a tree of nodes.
+///
+/// Unknown tags are dropped, keeping their content, and unclosed spans end
+/// with the cue text.
+pub fn parse_cue_text(text: &str) -> Vec<CueNode> {
+    // Open spans, each with the tag name it was opened with.
+    let mut stack: Vec<(CueSpan, &str)> = Vec::new();
+    let mut root: Vec<CueNode> = Vec::new();
+
+    fn push(stack: &mut [(CueSpan, &str)], root: &mut Vec<CueNode>, node: CueNode) {
+        match stack.last_mut() {
+            Some((span, _)) => span.children.push(node),
+            None => root.push(node),
+        }
+    }
+
+    for (_, token) in tokenize(text) {
+        match token {
+            Token::Text(text) => push(&mut stack, &mut root, CueNode::Text(decode_entities(text))),
+            Token::Timestamp(raw) => {
+                if let Ok(timestamp) = VttTimestamp::from_str(&raw[1..raw.len() - 1]) {
+                    push(&mut stack, &mut root, CueNode::Timestamp(timestamp));
+                }
+            }
+            Token::StartTag { raw, name } => {
+                let Some(kind) = SpanKind::from_tag_name(name) else {
+                    continue;
+                };
+                let inner = raw[1..].strip_suffix('>').unwrap_or(&raw[1..]);
+                let (head, annotation) = match inner.split_once(char::is_whitespace) {
+                    Some((head, rest)) => (head, Some(rest.trim())),
+                    None => (inner, None),
+                };
+                let classes = head
+                    .split('.')
+                    .skip(1)
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                let annotation = annotation.filter(|a| !a.is_empty()).map(decode_entities);
+                let span = CueSpan {
+                    kind,
+                    classes,
+                    annotation,
+                    children: Vec::new(),
+                };
+                stack.push((span, name));
+            }
+            Token::EndTag { name, .. } => {
+                let Some(i) = stack.iter().rposition(|(_, open)| *open == name) else {
+                    continue;
+                };
+                while stack.len() > i {
+                    if let Some((span, _)) = stack.pop() {
+                        push(&mut stack, &mut root, CueNode::Span(span));
+                    }
+                }
+            }
+        }
+    }
+
+    while let Some((span, _)) = stack.pop() {
+        push(&mut stack, &mut root, CueNode::Span(span));
+    }
+    root
+}
+
+/// Returns the non-empty lines of cue text with whitespace collapsed, each with
+/// the speaker of the voice span it starts in.
+pub(crate) fn speaker_lines(nodes: &[CueNode]) -> Vec<(Option<String>, String)> {
+    fn walk(
+        nodes: &[CueNode],
+        speaker: Option<&String>,
+        lines: &mut Vec<(Option<String>, String)>,
+    ) {
+        for node in nodes {
+            match node {
+                CueNode::Text(text) => {
+                    for (i, part) in text.split('\n').enumerate() {
+                        if i > 0 || lines.is_empty() {
+                            lines.push((None, String::new()));
+                        }
+                        if let Some(line) = lines.last_mut() {
+                            if line.0.is_none() && !part.trim().is_empty() {
+                                line.0 = speaker.cloned();
+                            }
+                            line.1.push_str(part);
+                        }
+                    }
+                }
+                CueNode::Timestamp(_) => {}
+                CueNode::Span(span) => {
+                    let speaker = match span.kind {
+                        SpanKind::Voice => span.annotation.as_ref(),
+                        _ => speaker,
+                    };
+                    walk(&span.children, speaker, lines);
+                }
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    walk(nodes, None, &mut lines);
+    lines
+        .into_iter()
+        .map(|(speaker, text)| {
+            (
+                speaker,
+                text.split_whitespace().collect::<Vec<_>>().join(" "),
+            )
+        })
+        .filter(|(_, text)| !text.is_empty())
+        .collect()
+}
+
+/// A lexical token of cue text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token<'a> {
+    /// Text between tags, with character references left undecoded.
+    Text(&'a str),
+    /// A start tag such as `<v.loud Bob>`, with the tag name (`v`).
+    StartTag { raw: &'a str, name: &'a str },
+    /// An end tag such as `</v>`, with the tag name (`v`).
+    EndTag { raw: &'a str, name: &'a str },
+    /// A timestamp tag such as `<00:00:01.500>`.
+    Timestamp(&'a str),
+}
+
+impl<'a> Token<'a> {
+    /// Returns the source text of the token.
+    pub(crate) fn raw(&self) -> &'a str {
+        match *self {
+            Token::Text(raw)
+            | Token::StartTag { raw, .. }
+            | Token::EndTag { raw, .. }
+            | Token::Timestamp(raw) => raw,
+        }
+    }
+}
+
+/// A token together with its byte offset in the cue text.
+pub(crate) type Spanned<'a> = (usize, Token<'a>);
+
+/// Splits cue text into text runs and tags.
+pub(crate) fn tokenize(text: &str) -> Vec<Spanned<'_>> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < text.len() {
+        let rest = &text[pos..];
+        if !rest.starts_with('<') {
+            let len = rest.find('<').unwrap_or(rest.len());
+            tokens.push((pos, Token::Text(&rest[..len])));
+            pos += len;
+            continue;
+        }
+
+        let len = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+        let raw = &rest[..len];
+        let inner = raw[1..].strip_suffix('>').unwrap_or(&raw[1..]);
+
+        let token = if let Some(end) = inner.strip_prefix('/') {
+            Token::EndTag {
+                raw,
+                name: end.trim(),
+            }
+        } else if inner.starts_with(|c: char| c.is_ascii_digit()) {
+            Token::Timestamp(raw)
+        } else {
+            let name_len = inner
+                .find(|c: char| c == '.' || c.is_whitespace())
+                .unwrap_or(inner.len());
+            Token::StartTag {
+                raw,
+                name: &inner[..name_len],
+            }
+        };
+        tokens.push((pos, token));
+        pos += len;
+    }
+
+    tokens
+}
+
+/// Returns the start tags left open after the given tokens, outermost first.
+pub(crate) fn open_tags<'a>(tokens: &[Spanned<'a>]) -> Vec<Token<'a>> {
+    let mut open: Vec<Token<'a>> = Vec::new();
+    for &(_, token) in tokens {
+        match token {
+            Token::StartTag { .. } => open.push(token),
+            Token::EndTag { name, .. } => {
+                if let Some(i) = open.iter().rposition(|t| tag_name(t) == Some(name)) {
+                    open.truncate(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    open
+}
+
+fn tag_name<'a>(token: &Token<'a>) -> Option<&'a str> {
+    match *token {
+        Token::StartTag { name, .. } | Token::EndTag { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+/// Returns the end tags closing the given open tags, innermost first.
+pub(crate) fn close_tags(open: &[Token<'_>]) -> String {
+    open.iter()
+        .rev()
+        .filter_map(tag_name)
+        .map(|name| format!("</{}>", name))
+        .collect()
+}
+
+/// Splits cue text at a byte offset outside any tag.
+///
+/// Tags still open at the split point are closed at the end of the first half
+/// and reopened at the start of the second half, so both halves stay balanced.
+/// Whitespace around the split point is trimmed.
+pub(crate) fn split_balanced(text: &str, offset: usize) -> (String, String) {
+    let tokens = tokenize(&text[..offset]);
+    let open = open_tags(&tokens);
+
+    let mut first = text[..offset].trim_end().to_string();
+    first.push_str(&close_tags(&open));
+
+    let mut second: String = open.iter().map(|t| t.raw()).collect();
+    second.push_str(text[offset..].trim_start());
+
+    (first, second)
+}
+
+/// Returns the text content of cue text, without tags and with character
+/// references decoded.
+pub(crate) fn plain_text(text: &str) -> String {
+    tokenize(text)
+        .into_iter()
+        .filter_map(|(_, token)| match token {
+            Token::Text(text) => Some(decode_entities(text)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Decodes the character references allowed in cue text.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let reference = rest
+            .find(';')
+            .and_then(|semi| decode_entity(&rest[1..semi]).map(|c| (c, semi)));
+        match reference {
+            Some((c, semi)) => {
+                decoded.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Renders a tree of nodes back into cue text, the inverse of `parse_cue_text`.
+#[cfg(feature = "serde")]
+pub(crate) fn render_cue_text(nodes: &[CueNode]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            CueNode::Text(content) => text.push_str(&escape_text(content)),
+            CueNode::Timestamp(timestamp) => text.push_str(&format!("<{}>", timestamp)),
+            CueNode::Span(span) => {
+                let name = span.kind.tag_name();
+                text.push('<');
+                text.push_str(name);
+                for class in &span.classes {
+                    text.push('.');
+                    text.push_str(class);
+                }
+                if let Some(ref annotation) = span.annotation {
+                    text.push(' ');
+                    text.push_str(&escape_text(annotation));
+                }
+                text.push('>');
+                text.push_str(&render_cue_text(&span.children));
+                text.push_str(&format!("</{}>", name));
+            }
+        }
+    }
+    text
+}
+
+/// Escapes the characters that cannot appear literally in cue text.
+pub(crate) fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        "lrm" => Some('\u{200e}'),
+        "rlm" => Some('\u{200f}'),
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> = tokenize("<v.loud Bob>Hi <00:00:01.000><b>there</b>")
+            .into_iter()
+            .map(|(_, t)| t)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::StartTag {
+                    raw: "<v.loud Bob>",
+                    name: "v"
+                },
+                Token::Text("Hi "),
+                Token::Timestamp("<00:00:01.000>"),
+                Token::StartTag {
+                    raw: "<b>",
+                    name: "b"
+                },
+                Token::Text("there"),
+                Token::EndTag {
+                    raw: "</b>",
+                    name: "b"
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_cue_text() {
+        let nodes = parse_cue_text("<v.loud Bob>Hi <x>you</x><00:00:01.000><b>there</v>");
+        assert_eq!(
+            nodes,
+            vec![CueNode::Span(CueSpan {
+                kind: SpanKind::Voice,
+                classes: vec!["loud".to_string()],
+                annotation: Some("Bob".to_string()),
+                children: vec![
+                    CueNode::Text("Hi ".to_string()),
+                    CueNode::Text("you".to_string()),
+                    CueNode::Timestamp(VttTimestamp::new(core::time::Duration::from_secs(1))),
+                    CueNode::Span(CueSpan {
+                        kind: SpanKind::Bold,
+                        classes: Vec::new(),
+                        annotation: None,
+                        children: vec![CueNode::Text("there".to_string())],
+                    }),
+                ],
+            })]
+        );
+    }
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(
+            plain_text("<v Bob>Fish &amp; chips\n<i>&lt;3</i> &#65;&x;"),
+            "Fish & chips\n<3 A&x;"
+        );
+    }
+
+    #[test]
+    fn test_split_balanced() {
+        let text = "<i>Hello <b>big</b> world</i>";
+        let (first, second) = split_balanced(text, text.find("world").unwrap());
+        assert_eq!(first, "<i>Hello <b>big</b></i>");
+        assert_eq!(second, "<i>world</i>");
+    }
+}
@@ -0,0 +1,337 @@
+//! Three-way merge of concurrently edited WebVTT files.
+
+use crate::diff::match_cues;
+use crate::{VttCue, WebVtt};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The kind of a merge conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConflictKind {
+    /// Both sides changed the identifier of the cue differently.
+    Identifier,
+    /// Both sides retimed the cue differently.
+    Timing,
+    /// Both sides changed the payload of the cue differently.
+    Text,
+    /// Both sides changed the settings of the cue differently.
+    Settings,
+    /// Our side deleted the cue while their side changed it.
+    DeletedByOurs,
+    /// Their side deleted the cue while our side changed it.
+    DeletedByTheirs,
+    /// Both sides added different cues at the same time.
+    BothAdded,
+    /// Both sides changed the header differently.
+    Header,
+    /// Both sides changed the style blocks differently.
+    Styles,
+}
+
+/// A conflict found by `WebVtt::merge3`, with the versions of the cue
+/// involved. The cues are `None` for header and style conflicts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    /// The kind of conflict.
+    pub kind: ConflictKind,
+    /// The cue in the common ancestor.
+    pub base: Option<VttCue>,
+    /// Our version of the cue.
+    pub ours: Option<VttCue>,
+    /// Their version of the cue.
+    pub theirs: Option<VttCue>,
+}
+
+/// The result of a three-way merge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeResult {
+    /// The merged file. Conflicting changes are resolved in favour of our side,
+    /// and cues changed by one side but deleted by the other are kept.
+    pub merged: WebVtt,
+    /// The conflicts found: header and style conflicts first, then cue
+    /// conflicts ordered by time.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    /// Returns `true` if the merge found no conflicts.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Picks the merged value of one field, or `None` on a conflict.
+fn merge_field<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> Option<T> {
+    if ours == base || ours == theirs {
+        Some(theirs.clone())
+    } else if theirs == base {
+        Some(ours.clone())
+    } else {
+        None
+    }
+}
+
+impl WebVtt {
+    /// Merges two versions of a file derived from this one.
+    ///
+    /// Cues are matched to this base version by identifier, timing and text,
+    /// as in `WebVtt::diff`. The identifier, timing, payload and settings of
+    /// each cue are merged separately, so one side can retime a cue while the
+    /// other fixes a typo in it.
+    pub fn merge3(&self, ours: &WebVtt, theirs: &WebVtt) -> MergeResult {
+        let mut conflicts = Vec::new();
+        let mut cues: Vec<VttCue> = Vec::new();
+
+        let conflict =
+            |kind, base: Option<&VttCue>, ours: Option<&VttCue>, theirs: Option<&VttCue>| {
+                MergeConflict {
+                    kind,
+                    base: base.cloned(),
+                    ours: ours.cloned(),
+                    theirs: theirs.cloned(),
+                }
+            };
+
+        let mut our_match = vec![None; self.cues.len()];
+        let mut our_added = vec![true; ours.cues.len()];
+        for (i, j) in match_cues(&self.cues, &ours.cues) {
+            our_match[i] = Some(&ours.cues[j]);
+            our_added[j] = false;
+        }
+        let mut their_match = vec![None; self.cues.len()];
+        let mut their_added = vec![true; theirs.cues.len()];
+        for (i, j) in match_cues(&self.cues, &theirs.cues) {
+            their_match[i] = Some(&theirs.cues[j]);
+            their_added[j] = false;
+        }
+
+        for (i, base) in self.cues.iter().enumerate() {
+            match (our_match[i], their_match[i]) {
+                (Some(our), Some(their)) => {
+                    let mut cue = our.clone();
+                    let mut field = |kind, merged: bool| {
+                        if !merged {
+                            conflicts.push(conflict(kind, Some(base), Some(our), Some(their)));
+                        }
+                    };
+
+                    let identifier =
+                        merge_field(&base.identifier, &our.identifier, &their.identifier);
+                    field(ConflictKind::Identifier, identifier.is_some());
+                    cue.identifier = identifier.unwrap_or_else(|| our.identifier.clone());
+
+                    let timing = merge_field(
+                        &(base.start, base.end),
+                        &(our.start, our.end),
+                        &(their.start, their.end),
+                    );
+                    field(ConflictKind::Timing, timing.is_some());
+                    (cue.start, cue.end) = timing.unwrap_or((our.start, our.end));
+
+                    let payload = merge_field(&base.payload, &our.payload, &their.payload);
+                    field(ConflictKind::Text, payload.is_some());
+                    cue.payload = payload.unwrap_or_else(|| our.payload.clone());
+
+                    let settings = merge_field(&base.settings, &our.settings, &their.settings);
+                    field(ConflictKind::Settings, settings.is_some());
+                    cue.settings = settings.unwrap_or_else(|| our.settings.clone());
+
+                    cues.push(cue);
+                }
+                (None, Some(their)) => {
+                    if their != base {
+                        conflicts.push(conflict(
+                            ConflictKind::DeletedByOurs,
+                            Some(base),
+                            None,
+                            Some(their),
+                        ));
+                        cues.push(their.clone());
+                    }
+                }
+                (Some(our), None) => {
+                    if our != base {
+                        conflicts.push(conflict(
+                            ConflictKind::DeletedByTheirs,
+                            Some(base),
+                            Some(our),
+                            None,
+                        ));
+                        cues.push(our.clone());
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+
+        // Cues added on both sides are matched against each other, so the same
+        // addition is only kept once.
+        let added_ours: Vec<VttCue> = ours
+            .cues
+            .iter()
+            .zip(&our_added)
+            .filter(|(_, a)| **a)
+            .map(|(c, _)| c.clone())
+            .collect();
+        let added_theirs: Vec<VttCue> = theirs
+            .cues
+            .iter()
+            .zip(&their_added)
+            .filter(|(_, a)| **a)
+            .map(|(c, _)| c.clone())
+            .collect();
+        let mut theirs_kept = vec![true; added_theirs.len()];
+        for (i, j) in match_cues(&added_ours, &added_theirs) {
+            theirs_kept[j] = false;
+            if added_ours[i] != added_theirs[j] {
+                conflicts.push(conflict(
+                    ConflictKind::BothAdded,
+                    None,
+                    Some(&added_ours[i]),
+                    Some(&added_theirs[j]),
+                ));
+            }
+        }
+        cues.extend(added_ours);
+        cues.extend(
+            added_theirs
+                .into_iter()
+                .zip(theirs_kept)
+                .filter(|(_, kept)| *kept)
+                .map(|(c, _)| c),
+        );
+        cues.sort_by_key(|cue| cue.start);
+
+        let conflict_time = |c: &MergeConflict| {
+            [&c.ours, &c.theirs, &c.base]
+                .into_iter()
+                .flatten()
+                .map(|cue| cue.start)
+                .next()
+        };
+        conflicts.sort_by_key(conflict_time);
+
+        let header = merge_field(&self.header, &ours.header, &theirs.header);
+        if header.is_none() {
+            conflicts.insert(0, conflict(ConflictKind::Header, None, None, None));
+        }
+        let styles = merge_field(&self.styles, &ours.styles, &theirs.styles);
+        if styles.is_none() {
+            conflicts.insert(0, conflict(ConflictKind::Styles, None, None, None));
+        }
+
+        MergeResult {
+            merged: WebVtt {
+                header: header.unwrap_or_else(|| ours.header.clone()),
+                styles: styles.unwrap_or_else(|| ours.styles.clone()),
+                cues,
+            },
+            conflicts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::string::ToString;
+    use core::str::FromStr;
+
+    const BASE: &str = "WEBVTT
+
+1
+00:00:01.000 --> 00:00:02.000
+Helo world
+
+2
+00:00:03.000 --> 00:00:04.000
+Second line
+
+3
+00:00:05.000 --> 00:00:06.000
+Third line";
+
+    #[test]
+    fn test_clean_merge() {
+        let base = WebVtt::from_str(BASE).unwrap();
+        // We fix the typo and delete cue 3, they retime cue 1 and add a cue.
+        let ours = WebVtt::from_str(
+            &BASE
+                .replace("Helo", "Hello")
+                .replace("\n\n3\n00:00:05.000 --> 00:00:06.000\nThird line", ""),
+        )
+        .unwrap();
+        let theirs = WebVtt::from_str(&format!(
+            "{}\n\n4\n00:00:07.000 --> 00:00:08.000\nFourth line",
+            BASE.replace(
+                "00:00:01.000 --> 00:00:02.000",
+                "00:00:01.200 --> 00:00:02.200"
+            )
+        ))
+        .unwrap();
+
+        let result = base.merge3(&ours, &theirs);
+        assert!(result.is_clean());
+        assert_eq!(
+            result.merged.to_string(),
+            "WEBVTT
+
+1
+00:00:01.200 --> 00:00:02.200
+Hello world
+
+2
+00:00:03.000 --> 00:00:04.000
+Second line
+
+4
+00:00:07.000 --> 00:00:08.000
+Fourth line"
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let base = WebVtt::from_str(BASE).unwrap();
+        let ours = WebVtt::from_str(
+            &BASE
+                .replace("Helo world", "Hello world")
+                .replace("Third line", "Third line!"),
+        )
+        .unwrap();
+        let theirs = WebVtt::from_str(
+            &BASE
+                .replace("Helo world", "Hello, world")
+                .replace("\n\n3\n00:00:05.000 --> 00:00:06.000\nThird line", ""),
+        )
+        .unwrap();
+
+        let result = base.merge3(&ours, &theirs);
+        let kinds: Vec<ConflictKind> = result.conflicts.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![ConflictKind::Text, ConflictKind::DeletedByTheirs]
+        );
+        assert_eq!(
+            result.conflicts[0].theirs.as_ref().unwrap().payload,
+            "Hello, world"
+        );
+        assert_eq!(result.merged.cues[0].payload, "Hello world");
+        assert_eq!(result.merged.cues[2].payload, "Third line!");
+    }
+
+    #[test]
+    fn test_both_added() {
+        let base = WebVtt::from_str("WEBVTT").unwrap();
+        let ours = WebVtt::from_str("WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nSame").unwrap();
+        let result = base.merge3(&ours, &ours);
+        assert!(result.is_clean());
+        assert_eq!(result.merged.cues.len(), 1);
+
+        let theirs = WebVtt::from_str("WEBVTT\n\n00:00:01.000 --> 00:00:02.500\nSame").unwrap();
+        let result = base.merge3(&ours, &theirs);
+        assert_eq!(result.conflicts[0].kind, ConflictKind::BothAdded);
+        assert_eq!(result.merged.cues, ours.cues);
+    }
+}
@@ -0,0 +1,269 @@
+//! Detection and resolution of overlapping cues.
+
+use crate::{LineSetting, VttCue, VttSettings, VttTimestamp, WebVtt};
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A pair of cues whose time ranges overlap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueOverlap {
+    /// Index of the cue that starts first.
+    pub first: usize,
+    /// Index of the cue that starts second.
+    pub second: usize,
+    /// Start of the overlapping range.
+    pub start: VttTimestamp,
+    /// End of the overlapping range.
+    pub end: VttTimestamp,
+}
+
+/// Strategies for resolving overlapping cues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapStrategy {
+    /// Ends each cue where the next cue starts. Cues starting at the same time are merged.
+    Trim,
+    /// Merges each group of overlapping cues into a single cue.
+    Merge,
+    /// Keeps the timings and gives overlapping cues distinct `line` settings.
+    Stack,
+}
+
+impl WebVtt {
+    /// Returns all pairs of overlapping cues, ordered by the start of the overlap.
+    pub fn find_overlaps(&self) -> Vec<CueOverlap> {
+        let index = self.index();
+        let mut overlaps = Vec::new();
+
+        for (i, cue) in self.cues.iter().enumerate() {
+            for j in index.overlapping(&cue.start, &cue.end) {
+                let other = &self.cues[j];
+                if (other.start, j) <= (cue.start, i) {
+                    continue;
+                }
+                overlaps.push(CueOverlap {
+                    first: i,
+                    second: j,
+                    start: other.start,
+                    end: cue.end.min(other.end),
+                });
+            }
+        }
+
+        overlaps.sort_by_key(|o| (o.start, o.first, o.second));
+        overlaps
+    }
+
+    /// Resolves overlapping cues using the given strategy.
+    ///
+    /// Returns the number of overlaps found before resolving them.
+    pub fn resolve_overlaps(&mut self, strategy: OverlapStrategy) -> usize {
+        let overlaps = self.find_overlaps();
+        if overlaps.is_empty() {
+            return 0;
+        }
+
+        match strategy {
+            OverlapStrategy::Trim => self.trim_overlaps(),
+            OverlapStrategy::Merge => self.merge_overlaps(),
+            OverlapStrategy::Stack => self.stack_overlaps(&overlaps),
+        }
+
+        overlaps.len()
+    }
+
+    fn trim_overlaps(&mut self) {
+        // Cues sharing a start time cannot be trimmed apart.
+        self.merge_groups(|cue, bounds| cue.start == bounds.0);
+
+        let order = self.start_order();
+        for (pos, &i) in order.iter().enumerate() {
+            if let Some(&next) = order.get(pos + 1) {
+                let next_start = self.cues[next].start;
+                if self.cues[i].end > next_start {
+                    self.cues[i].end = next_start;
+                }
+            }
+        }
+    }
+
+    fn merge_overlaps(&mut self) {
+        self.merge_groups(|cue, bounds| cue.start < bounds.1);
+    }
+
+    /// Merges runs of cues, in start order, for which `joins` holds.
+    ///
+    /// `joins` receives the next cue and the `(start, end)` of the current group.
+    /// Each merged cue takes the place of the group's first cue in document order.
+    fn merge_groups<F>(&mut self, joins: F)
+    where
+        F: Fn(&VttCue, (VttTimestamp, VttTimestamp)) -> bool,
+    {
+        let order = self.start_order();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut bounds = (VttTimestamp::default(), VttTimestamp::default());
+
+        for &i in &order {
+            let cue = &self.cues[i];
+            match groups.last_mut() {
+                Some(group) if joins(cue, bounds) => {
+                    group.push(i);
+                    bounds.1 = bounds.1.max(cue.end);
+                }
+                _ => {
+                    groups.push(vec![i]);
+                    bounds = (cue.start, cue.end);
+                }
+            }
+        }
+
+        let mut merged: Vec<Option<VttCue>> = vec![None; self.cues.len()];
+        for group in groups {
+            let Some(&first) = group.iter().min() else {
+                continue;
+            };
+            let cue = group[1..]
+                .iter()
+                .fold(self.cues[group[0]].clone(), |acc, &i| {
+                    acc.merge(&self.cues[i])
+                });
+            merged[first] = Some(cue);
+        }
+        self.cues = merged.into_iter().flatten().collect();
+    }
+
+    fn stack_overlaps(&mut self, overlaps: &[CueOverlap]) {
+        let involved: BTreeSet<usize> = overlaps.iter().flat_map(|o| [o.first, o.second]).collect();
+
+        // Occupied line ranges of the stacked cues still on screen: (end, offset, height).
+        let mut active: Vec<(VttTimestamp, usize, usize)> = Vec::new();
+
+        for i in self.start_order() {
+            if !involved.contains(&i) {
+                continue;
+            }
+            let cue = &mut self.cues[i];
+            active.retain(|&(end, _, _)| end > cue.start);
+
+            let height = cue.payload.trim().lines().count().max(1);
+            let mut offset = 0;
+            while let Some(&(_, o, h)) = active
+                .iter()
+                .find(|&&(_, o, h)| offset < o + h && o < offset + height)
+            {
+                offset = o + h;
+            }
+            active.push((cue.end, offset, height));
+
+            cue.settings.get_or_insert_with(VttSettings::default).line =
+                Some(LineSetting::Number(-((offset + height) as i32)));
+        }
+    }
+
+    /// Returns cue indices ordered by start time, then by document order.
+    fn start_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.cues.len()).collect();
+        order.sort_by_key(|&i| self.cues[i].start);
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ts;
+    use core::str::FromStr;
+
+    const OVERLAPPING: &str = "WEBVTT
+
+00:00:00.000 --> 00:00:04.000
+First
+
+00:00:03.000 --> 00:00:06.000
+Second
+
+00:00:05.000 --> 00:00:07.000
+Third
+
+00:00:08.000 --> 00:00:09.000
+Fourth";
+
+    #[test]
+    fn test_find_overlaps() {
+        let vtt = WebVtt::from_str(OVERLAPPING).unwrap();
+        let overlaps = vtt.find_overlaps();
+
+        assert_eq!(
+            overlaps,
+            vec![
+                CueOverlap {
+                    first: 0,
+                    second: 1,
+                    start: ts(3_000),
+                    end: ts(4_000),
+                },
+                CueOverlap {
+                    first: 1,
+                    second: 2,
+                    start: ts(5_000),
+                    end: ts(6_000),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_trim() {
+        let mut vtt = WebVtt::from_str(OVERLAPPING).unwrap();
+        assert_eq!(vtt.resolve_overlaps(OverlapStrategy::Trim), 2);
+
+        assert_eq!(vtt.cues.len(), 4);
+        assert_eq!(vtt.cues[0].end, ts(3_000));
+        assert_eq!(vtt.cues[1].end, ts(5_000));
+        assert_eq!(vtt.cues[2].end, ts(7_000));
+        assert!(vtt.find_overlaps().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_trim_same_start() {
+        let mut vtt = WebVtt::from_str(
+            "WEBVTT
+
+00:00:01.000 --> 00:00:02.000
+One
+
+00:00:01.000 --> 00:00:03.000
+Two",
+        )
+        .unwrap();
+        vtt.resolve_overlaps(OverlapStrategy::Trim);
+
+        assert_eq!(vtt.cues.len(), 1);
+        assert_eq!(vtt.cues[0].payload, "One\nTwo");
+        assert_eq!(vtt.cues[0].end, ts(3_000));
+    }
+
+    #[test]
+    fn test_resolve_merge() {
+        let mut vtt = WebVtt::from_str(OVERLAPPING).unwrap();
+        vtt.resolve_overlaps(OverlapStrategy::Merge);
+
+        assert_eq!(vtt.cues.len(), 2);
+        assert_eq!(vtt.cues[0].payload, "First\nSecond\nThird");
+        assert_eq!(vtt.cues[0].start, ts(0));
+        assert_eq!(vtt.cues[0].end, ts(7_000));
+        assert_eq!(vtt.cues[1].payload, "Fourth");
+    }
+
+    #[test]
+    fn test_resolve_stack() {
+        let mut vtt = WebVtt::from_str(OVERLAPPING).unwrap();
+        vtt.resolve_overlaps(OverlapStrategy::Stack);
+
+        let line = |i: usize| vtt.cues[i].settings.as_ref().and_then(|s| s.line.clone());
+        assert_eq!(line(0), Some(LineSetting::Number(-1)));
+        assert_eq!(line(1), Some(LineSetting::Number(-2)));
+        assert_eq!(line(2), Some(LineSetting::Number(-1)));
+        assert_eq!(line(3), None);
+    }
+}
@@ -0,0 +1,500 @@
+//! Line breaking and reflow of cue payloads.
+
+use crate::markup::{self, Token};
+use crate::{VttCue, VttTimestamp, WebVtt};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// Words that read badly at the end of a line, apart from the word they introduce.
+const FUNCTION_WORDS: &[&str] = &[
+    "a", "an", "the", "to", "of", "in", "on", "at", "by", "for", "from", "with", "and", "or",
+    "but", "nor", "as", "if", "that", "my", "your", "his", "her", "its", "our", "their", "mr.",
+    "mrs.", "ms.", "dr.", "st.",
+];
+
+/// Cost of breaking after a function word.
+const FUNCTION_WORD_PENALTY: i64 = 400;
+/// Cost of breaking between two capitalized words, which are likely a name.
+const NAME_PENALTY: i64 = 600;
+/// Reward for breaking after punctuation that ends a clause.
+const PUNCTUATION_BONUS: i64 = 300;
+/// Cost per character of a line that breaks the requested shape.
+const SHAPE_PENALTY: i64 = 40;
+
+/// The shape of a multi-line cue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineBalance {
+    /// Lines of similar length, with the bottom line the longest.
+    #[default]
+    BottomHeavy,
+    /// Every line at least as long as the line above it.
+    Pyramid,
+}
+
+/// Options for `VttCue::reflow` and `WebVtt::reflow`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflowConfig {
+    /// Maximum number of characters on a line.
+    pub max_line_length: usize,
+    /// Maximum number of lines in a cue.
+    pub max_lines: usize,
+    /// The preferred shape of multi-line cues.
+    pub balance: LineBalance,
+}
+
+impl Default for ReflowConfig {
+    fn default() -> Self {
+        ReflowConfig {
+            max_line_length: 42,
+            max_lines: 2,
+            balance: LineBalance::default(),
+        }
+    }
+}
+
+/// A word of cue text together with any tags attached to it.
+#[derive(Debug)]
+struct Word {
+    /// The source text of the word, including attached tags.
+    raw: String,
+    /// Number of visible characters.
+    width: usize,
+    /// Cost of breaking the line after this word.
+    penalty: i64,
+}
+
+impl VttCue {
+    /// Re-wraps the payload to fit the configured line length and line count.
+    ///
+    /// Text that does not fit in one cue is split into several cues, with time
+    /// allocated in proportion to their text. Split cues are identified like
+    /// those of `VttCue::split_at_time`, e.g. `id-1`, `id-2`.
+    pub fn reflow(&self, config: &ReflowConfig) -> Vec<VttCue> {
+        let words = words(&self.payload);
+        if words.is_empty() {
+            return vec![self.clone()];
+        }
+
+        let chunks = chunk(&words, config);
+        let total: usize = words.iter().map(|w| w.width).sum();
+        let start = self.start.as_duration().as_millis();
+        let length = self.duration().as_millis();
+
+        let mut cues = Vec::with_capacity(chunks.len());
+        let mut open: Vec<String> = Vec::new();
+        let mut done = 0;
+
+        for (n, range) in chunks.iter().enumerate() {
+            let chunk = &words[range.0..range.1];
+            let lines = break_lines(chunk, config);
+
+            let mut payload: String = open.concat();
+            for (i, line) in lines.iter().enumerate() {
+                if i > 0 {
+                    payload.push('\n');
+                }
+                let raw: Vec<&str> = chunk[line.0..line.1]
+                    .iter()
+                    .map(|w| w.raw.as_str())
+                    .collect();
+                payload.push_str(&raw.join(" "));
+            }
+
+            // Markup left open continues in the next cue.
+            if n + 1 < chunks.len() {
+                let tokens = markup::tokenize(&payload);
+                let still_open = markup::open_tags(&tokens);
+                let closing = markup::close_tags(&still_open);
+                open = still_open.iter().map(|t| t.raw().to_string()).collect();
+                payload.push_str(&closing);
+            }
+
+            let time = |done: usize| {
+                let elapsed = (length * done as u128)
+                    .checked_div(total as u128)
+                    .unwrap_or(0);
+                VttTimestamp::new(Duration::from_millis((start + elapsed) as u64))
+            };
+            let cue_start = time(done);
+            done += chunk.iter().map(|w| w.width).sum::<usize>();
+            let cue_end = if n + 1 == chunks.len() {
+                self.end
+            } else {
+                time(done)
+            };
+
+            cues.push(VttCue {
+                identifier: self.identifier.clone(),
+                start: cue_start,
+                end: cue_end,
+                settings: self.settings.clone(),
+                payload,
+            });
+        }
+
+        if cues.len() > 1 {
+            for (n, cue) in cues.iter_mut().enumerate() {
+                cue.identifier = cue.identifier.take().map(|id| format!("{}-{}", id, n + 1));
+            }
+        }
+        cues
+    }
+}
+
+impl WebVtt {
+    /// Re-wraps the payload of every cue, splitting cues whose text does not fit.
+    pub fn reflow(&mut self, config: &ReflowConfig) {
+        self.cues = self.cues.iter().flat_map(|c| c.reflow(config)).collect();
+    }
+}
+
+/// Splits cue text into words, attaching each tag to its neighbouring word.
+fn words(payload: &str) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut current = String::new();
+
+    for (_, token) in markup::tokenize(payload) {
+        match token {
+            Token::Text(text) => {
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        finish_word(&mut current, &mut words);
+                    } else {
+                        current.push(c);
+                    }
+                }
+            }
+            Token::StartTag { raw, .. } | Token::Timestamp(raw) => current.push_str(raw),
+            Token::EndTag { raw, .. } => match words.last_mut() {
+                Some(last) if current.is_empty() => last.raw.push_str(raw),
+                _ => current.push_str(raw),
+            },
+        }
+    }
+    finish_word(&mut current, &mut words);
+
+    for i in 0..words.len().saturating_sub(1) {
+        words[i].penalty = break_penalty(&words[i], &words[i + 1]);
+    }
+    words
+}
+
+fn finish_word(current: &mut String, words: &mut Vec<Word>) {
+    if current.is_empty() {
+        return;
+    }
+    let width = markup::plain_text(current).chars().count();
+    match words.last_mut() {
+        // A run of tags with no text belongs to the previous word.
+        Some(last) if width == 0 => last.raw.push_str(current),
+        _ => words.push(Word {
+            raw: current.clone(),
+            width,
+            penalty: 0,
+        }),
+    }
+    current.clear();
+}
+
+fn break_penalty(word: &Word, next: &Word) -> i64 {
+    let text = markup::plain_text(&word.raw);
+    let next_text = markup::plain_text(&next.raw);
+    let capitalized = |s: &str| s.chars().next().is_some_and(char::is_uppercase);
+
+    if text.ends_with(['.', ',', '?', '!', ';', ':']) {
+        return -PUNCTUATION_BONUS;
+    }
+    if FUNCTION_WORDS.contains(&text.to_lowercase().as_str()) {
+        return FUNCTION_WORD_PENALTY;
+    }
+    if capitalized(&text) && capitalized(&next_text) {
+        return NAME_PENALTY;
+    }
+    0
+}
+
+/// Width of the words laid out on one line.
+fn line_width(words: &[Word]) -> usize {
+    words.iter().map(|w| w.width).sum::<usize>() + words.len().saturating_sub(1)
+}
+
+/// Returns the end of the longest run of words from `from` that fits in one cue.
+///
+/// At least one word is always taken, even if it is too long for a line.
+fn reach(words: &[Word], from: usize, config: &ReflowConfig) -> usize {
+    let mut end = from;
+    let mut lines = 0;
+    while end < words.len() && lines < config.max_lines.max(1) {
+        let line_start = end;
+        end += 1;
+        while end < words.len() && line_width(&words[line_start..=end]) <= config.max_line_length {
+            end += 1;
+        }
+        lines += 1;
+    }
+    end
+}
+
+/// Divides words into cue-sized chunks of similar length, returned as ranges.
+fn chunk(words: &[Word], config: &ReflowConfig) -> Vec<(usize, usize)> {
+    let n = words.len();
+    let reaches: Vec<usize> = (0..n).map(|i| reach(words, i, config)).collect();
+
+    // The fewest chunks possible, found by taking the longest run each time.
+    let mut count = 0;
+    let mut pos = 0;
+    while pos < n {
+        pos = reaches[pos];
+        count += 1;
+    }
+    if count == 1 {
+        return vec![(0, n)];
+    }
+
+    // Among splits into that many chunks, prefer even lengths and good breaks.
+    let target = line_width(words) as i64 / count as i64;
+    let cost = |i: usize, j: usize| {
+        let deviation = line_width(&words[i..j]) as i64 - target;
+        let penalty = if j < n { words[j - 1].penalty } else { 0 };
+        deviation * deviation + penalty * 4
+    };
+
+    let mut best: Vec<Vec<Option<(i64, usize)>>> = vec![vec![None; n + 1]; count + 1];
+    best[0][0] = Some((0, 0));
+    for c in 1..=count {
+        let mut row: Vec<Option<(i64, usize)>> = vec![None; n + 1];
+        for (i, prev) in best[c - 1].iter().enumerate().take(n) {
+            let Some((so_far, _)) = *prev else {
+                continue;
+            };
+            for (j, slot) in row.iter_mut().enumerate().take(reaches[i] + 1).skip(i + 1) {
+                let total = so_far + cost(i, j);
+                if slot.is_none_or(|(b, _)| total < b) {
+                    *slot = Some((total, i));
+                }
+            }
+        }
+        best[c] = row;
+    }
+
+    let mut chunks = Vec::with_capacity(count);
+    let mut end = n;
+    for c in (1..=count).rev() {
+        let start = best[c][end].map_or(0, |(_, i)| i);
+        chunks.push((start, end));
+        end = start;
+    }
+    chunks.reverse();
+    chunks
+}
+
+/// Breaks a chunk of words into balanced lines, returned as ranges.
+fn break_lines(words: &[Word], config: &ReflowConfig) -> Vec<(usize, usize)> {
+    if line_width(words) <= config.max_line_length || words.len() == 1 {
+        return vec![(0, words.len())];
+    }
+
+    // The fewest lines the words fit on.
+    let mut lines = 0;
+    let mut pos = 0;
+    while pos < words.len() {
+        let start = pos;
+        pos += 1;
+        while pos < words.len() && line_width(&words[start..=pos]) <= config.max_line_length {
+            pos += 1;
+        }
+        lines += 1;
+    }
+
+    let ends = search_breaks(words, config, lines).unwrap_or_else(|| vec![words.len()]);
+    let mut start = 0;
+    ends.into_iter()
+        .map(|end| {
+            let line = (start, end);
+            start = end;
+            line
+        })
+        .collect()
+}
+
+/// The state of `search_breaks` after placing some lines: the start of the
+/// topmost line, and the width the next line up is compared with to keep the
+/// requested shape.
+type BreakState = (usize, usize);
+
+/// Finds the cheapest way of breaking the words into the given number of
+/// lines, returning the end of each line.
+///
+/// Lines are placed from the bottom up. A line above others only adds cost
+/// for its length, its break and how much it is longer than the bottom line
+/// (`BottomHeavy`) or the line below it (`Pyramid`), so the cheapest layouts
+/// are kept per state instead of trying every combination of breaks.
+fn search_breaks(words: &[Word], config: &ReflowConfig, lines: usize) -> Option<Vec<usize>> {
+    let n = words.len();
+    let fits = |start: usize, end: usize| line_width(&words[start..end]) <= config.max_line_length;
+    // The line widths add up to the same total for any layout of this many lines.
+    let mean = (line_width(words) + 1 - lines) as i64 / lines as i64;
+
+    // For each number of lines placed, the cheapest cost of reaching a state
+    // and the state it was reached from.
+    let mut layers: Vec<BTreeMap<BreakState, (i64, BreakState)>> = Vec::with_capacity(lines);
+    let mut bottom = BTreeMap::new();
+    for start in 1..n {
+        if fits(start, n) || start + 1 == n {
+            let width = line_width(&words[start..]);
+            let deviation = width as i64 - mean;
+            bottom.insert((start, width), (deviation * deviation, (n, 0)));
+        }
+    }
+    layers.push(bottom);
+
+    for _ in 1..lines {
+        let mut layer: BTreeMap<BreakState, (i64, BreakState)> = BTreeMap::new();
+        for (&(end, compared), &(so_far, _)) in layers.last()? {
+            for start in (0..end).rev() {
+                // A word too long for any line goes on a line of its own.
+                if !fits(start, end) && start + 1 < end {
+                    break;
+                }
+                let width = line_width(&words[start..end]);
+                let deviation = width as i64 - mean;
+                let shape = (width as i64 - compared as i64).max(0) * SHAPE_PENALTY;
+                let cost = so_far + deviation * deviation + shape + words[end - 1].penalty;
+                let next = match config.balance {
+                    LineBalance::BottomHeavy => (start, compared),
+                    LineBalance::Pyramid => (start, width),
+                };
+                let slot = layer.entry(next).or_insert((i64::MAX, (end, compared)));
+                if cost < slot.0 {
+                    *slot = (cost, (end, compared));
+                }
+            }
+        }
+        layers.push(layer);
+    }
+
+    let mut state = layers
+        .last()?
+        .iter()
+        .filter(|((start, _), _)| *start == 0)
+        .min_by_key(|(_, (cost, _))| *cost)
+        .map(|(&state, _)| state)?;
+    let mut ends = Vec::with_capacity(lines);
+    for layer in layers.iter().rev() {
+        let (_, previous) = layer[&state];
+        ends.push(previous.0);
+        state = previous;
+    }
+    Some(ends)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::cue_with_id;
+
+    #[test]
+    fn test_short_text_stays_on_one_line() {
+        let cues = cue_with_id("c", 0, 1_000, "Just a few\nwords").reflow(&ReflowConfig::default());
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].payload, "Just a few words");
+        assert_eq!(cues[0].identifier.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn test_balanced_bottom_heavy() {
+        let config = ReflowConfig {
+            max_line_length: 30,
+            ..ReflowConfig::default()
+        };
+        let cues = cue_with_id(
+            "c",
+            0,
+            2_000,
+            "I think we should go to the market before it closes",
+        )
+        .reflow(&config);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(
+            cues[0].payload,
+            "I think we should go\nto the market before it closes"
+        );
+    }
+
+    #[test]
+    fn test_keeps_names_and_tags_together() {
+        let config = ReflowConfig {
+            max_line_length: 20,
+            ..ReflowConfig::default()
+        };
+        let cues = cue_with_id(
+            "c",
+            0,
+            2_000,
+            "<v Ann Lee>We met Mary Jane <i>last night</i> again",
+        )
+        .reflow(&config);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(
+            cues[0].payload,
+            "<v Ann Lee>We met Mary Jane\n<i>last night</i> again"
+        );
+    }
+
+    #[test]
+    fn test_splits_into_timed_cues() {
+        let config = ReflowConfig {
+            max_line_length: 10,
+            max_lines: 1,
+            ..ReflowConfig::default()
+        };
+        let cues = cue_with_id("c", 1_000, 3_000, "<i>aaaa bbbb cccc dddd</i>").reflow(&config);
+
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].payload, "<i>aaaa bbbb</i>");
+        assert_eq!(cues[1].payload, "<i>cccc dddd</i>");
+        assert_eq!(cues[0].identifier.as_deref(), Some("c-1"));
+        assert_eq!(cues[0].end.as_duration(), Duration::from_millis(2_000));
+        assert_eq!(cues[1].start.as_duration(), Duration::from_millis(2_000));
+        assert_eq!(cues[1].end.as_duration(), Duration::from_millis(3_000));
+    }
+
+    #[test]
+    fn test_many_lines() {
+        let config = ReflowConfig {
+            max_lines: 20,
+            ..ReflowConfig::default()
+        };
+        let words = [
+            "the", "quick", "brown", "fox", "jumps", "over", "a", "lazy", "dog",
+        ];
+        let text: Vec<&str> = words.iter().cycle().take(150).copied().collect();
+        let cues = cue_with_id("c", 0, 60_000, &text.join(" ")).reflow(&config);
+
+        assert_eq!(cues.len(), 1);
+        let lines: Vec<&str> = cues[0].payload.lines().collect();
+        assert_eq!(lines.join(" "), text.join(" "));
+        assert!(lines.len() <= 20);
+        assert!(lines.iter().all(|line| line.len() <= 42));
+    }
+
+    #[test]
+    fn test_long_word_on_its_own_line() {
+        let config = ReflowConfig {
+            max_line_length: 10,
+            max_lines: 3,
+            ..ReflowConfig::default()
+        };
+        let cues = cue_with_id("c", 0, 2_000, "bbbbbbbbbbbbbbb aaaa").reflow(&config);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].payload, "bbbbbbbbbbbbbbb\naaaa");
+
+        let cues = cue_with_id("c", 0, 2_000, "aaaa bbbbbbbbbbbbbbb cccc").reflow(&config);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].payload, "aaaa\nbbbbbbbbbbbbbbb\ncccc");
+    }
+}
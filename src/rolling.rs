@@ -0,0 +1,143 @@
+//! Normalization of roll-up captions such as YouTube auto-captions.
+
+use crate::markup::{escape_text, plain_text};
+use crate::{VttCue, VttTimestamp, WebVtt};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A line of the collapsed captions.
+struct Line {
+    start: VttTimestamp,
+    end: VttTimestamp,
+    text: String,
+}
+
+impl WebVtt {
+    /// Collapses roll-up captions into non-overlapping cues of one line each.
+    ///
+    /// Auto-generated captions, as produced by YouTube, repeat each line across
+    /// consecutive cues while the next line is typed in with karaoke timestamps.
+    /// Each line is kept once, from the cue it first appears in until the next
+    /// line appears or it scrolls off screen. Markup, identifiers and settings
+    /// are dropped.
+    pub fn collapse_rolling_captions(&mut self) {
+        let mut order: Vec<&VttCue> = self.cues.iter().collect();
+        order.sort_by_key(|cue| cue.start);
+
+        let mut lines: Vec<Line> = Vec::new();
+        let mut previous: Vec<String> = Vec::new();
+
+        for cue in order {
+            let current: Vec<String> = plain_text(&cue.payload)
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .filter(|line| !line.is_empty())
+                .collect();
+
+            // The lines carried over from the previous cue, which still show.
+            let carried = (0..=previous.len().min(current.len()))
+                .rev()
+                .find(|&k| previous[previous.len() - k..] == current[..k])
+                .unwrap_or(0);
+            let visible = lines.len().saturating_sub(carried);
+            for line in &mut lines[visible..] {
+                line.end = line.end.max(cue.end);
+            }
+
+            for text in &current[carried..] {
+                lines.push(Line {
+                    start: cue.start,
+                    end: cue.end,
+                    text: text.clone(),
+                });
+            }
+            previous = current;
+        }
+
+        for i in 1..lines.len() {
+            let next = lines[i].start;
+            let line = &mut lines[i - 1];
+            line.end = line.end.min(next);
+        }
+
+        self.cues = lines
+            .into_iter()
+            .filter(|line| line.end > line.start)
+            .map(|line| VttCue {
+                identifier: None,
+                start: line.start,
+                end: line.end,
+                settings: None,
+                payload: escape_text(&line.text),
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use core::str::FromStr;
+
+    const AUTO_CAPTIONS: &str = "WEBVTT
+Kind: captions
+Language: en
+
+00:00:00.000 --> 00:00:02.310 align:start position:0%
+ 
+hello<00:00:00.320><c> world</c><00:00:00.640><c> this</c>
+
+00:00:02.310 --> 00:00:02.320 align:start position:0%
+hello world this
+ 
+
+00:00:02.320 --> 00:00:05.000 align:start position:0%
+hello world this
+is<00:00:02.800><c> fish</c><00:00:03.100><c> &amp; chips</c>
+
+00:00:05.000 --> 00:00:05.010 align:start position:0%
+is fish &amp; chips
+ 
+
+00:00:05.010 --> 00:00:07.000 align:start position:0%
+is fish &amp; chips
+is fish &amp; chips
+";
+
+    #[test]
+    fn test_collapse_rolling_captions() {
+        let mut vtt = WebVtt::from_str(AUTO_CAPTIONS).unwrap();
+        assert_eq!(vtt.cues.len(), 5);
+        vtt.collapse_rolling_captions();
+
+        let cues: Vec<(u128, u128, &str)> = vtt
+            .cues
+            .iter()
+            .map(|c| {
+                (
+                    c.start.as_duration().as_millis(),
+                    c.end.as_duration().as_millis(),
+                    c.payload.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            cues,
+            vec![
+                (0, 2320, "hello world this"),
+                (2320, 5010, "is fish &amp; chips"),
+                (5010, 7000, "is fish &amp; chips"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collapse_plain_captions() {
+        let text =
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nOne\n\n00:00:03.000 --> 00:00:04.000\nTwo";
+        let mut vtt = WebVtt::from_str(text).unwrap();
+        vtt.collapse_rolling_captions();
+        assert_eq!(vtt, WebVtt::from_str(text).unwrap());
+    }
+}
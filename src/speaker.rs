@@ -0,0 +1,261 @@
+//! Speakers identified by voice spans (`<v Name>`).
+
+use crate::markup::{
+    close_tags, decode_entities, escape_text, open_tags, parse_cue_text, speaker_lines, tokenize,
+    Token,
+};
+use crate::{VttCue, WebVtt};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// Statistics about a speaker, as returned by `WebVtt::speakers`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeakerStats {
+    /// The name given in the voice spans.
+    pub name: String,
+    /// The number of cues in which the speaker talks.
+    pub cues: usize,
+    /// The total time during which cues of the speaker are displayed. Time
+    /// covered by several of the speaker's cues is counted once.
+    pub talk_time: Duration,
+}
+
+impl VttCue {
+    /// Returns the distinct speakers of the cue, in order of appearance.
+    pub fn speakers(&self) -> Vec<String> {
+        let mut speakers: Vec<String> = Vec::new();
+        for (speaker, _) in voice_lines(&self.payload) {
+            if let Some(speaker) = speaker {
+                if !speakers.contains(&speaker) {
+                    speakers.push(speaker);
+                }
+            }
+        }
+        speakers
+    }
+}
+
+impl WebVtt {
+    /// Returns the speakers of the file in order of first appearance, with
+    /// their cue counts and talk time.
+    pub fn speakers(&self) -> Vec<SpeakerStats> {
+        let mut speakers: Vec<(String, Vec<(Duration, Duration)>)> = Vec::new();
+        for cue in &self.cues {
+            let interval = (cue.start.as_duration(), cue.end.as_duration());
+            for name in cue.speakers() {
+                match speakers.iter_mut().find(|(n, _)| *n == name) {
+                    Some((_, intervals)) => intervals.push(interval),
+                    None => speakers.push((name, vec![interval])),
+                }
+            }
+        }
+
+        speakers
+            .into_iter()
+            .map(|(name, mut intervals)| {
+                let cues = intervals.len();
+                intervals.sort();
+                let mut talk_time = Duration::ZERO;
+                let mut covered = Duration::ZERO;
+                for (start, end) in intervals {
+                    let start = start.max(covered);
+                    if end > start {
+                        talk_time += end - start;
+                        covered = end;
+                    }
+                }
+                SpeakerStats {
+                    name,
+                    cues,
+                    talk_time,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a copy of the file containing only what the given speaker says.
+    ///
+    /// Cues of other speakers are dropped. In cues shared by several speakers,
+    /// only the lines of the given speaker are kept.
+    pub fn filter_speaker(&self, name: &str) -> WebVtt {
+        let cues = self
+            .cues
+            .iter()
+            .filter_map(|cue| {
+                let lines = voice_lines(&cue.payload);
+                let spoken: Vec<&str> = lines
+                    .iter()
+                    .filter(|(speaker, _)| speaker.as_deref() == Some(name))
+                    .map(|(_, line)| line.as_str())
+                    .collect();
+                if spoken.is_empty() {
+                    return None;
+                }
+                let mut cue = cue.clone();
+                if cue.speakers().len() > 1 {
+                    cue.payload = spoken.join("\n");
+                }
+                Some(cue)
+            })
+            .collect();
+
+        WebVtt {
+            header: self.header.clone(),
+            styles: self.styles.clone(),
+            cues,
+        }
+    }
+
+    /// Splits the file into one file per speaker, in order of first appearance.
+    pub fn split_by_speaker(&self) -> Vec<(String, WebVtt)> {
+        self.speakers()
+            .into_iter()
+            .map(|stats| {
+                let vtt = self.filter_speaker(&stats.name);
+                (stats.name, vtt)
+            })
+            .collect()
+    }
+
+    /// Renames a speaker in all voice spans and returns the number of cues changed.
+    pub fn rename_speaker(&mut self, from: &str, to: &str) -> usize {
+        let mut changed = 0;
+        for cue in &mut self.cues {
+            let mut payload = String::with_capacity(cue.payload.len());
+            for (_, token) in tokenize(&cue.payload) {
+                let renamed = match token {
+                    Token::StartTag { raw, name: "v" } => {
+                        let inner = raw[1..].strip_suffix('>').unwrap_or(&raw[1..]);
+                        match inner.split_once(char::is_whitespace) {
+                            Some((head, annotation))
+                                if decode_entities(annotation.trim()) == from =>
+                            {
+                                Some(format!("<{} {}>", head, escape_text(to)))
+                            }
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                payload.push_str(renamed.as_deref().unwrap_or(token.raw()));
+            }
+            if payload != cue.payload {
+                cue.payload = payload;
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+/// Splits cue text into balanced lines, each with its speaker.
+///
+/// Tags open at the start of a line are reopened on it, and tags still open at
+/// its end are closed, so each line can stand on its own.
+fn voice_lines(text: &str) -> Vec<(Option<String>, String)> {
+    let tokens = tokenize(text);
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    for line in text.split('\n') {
+        let end = start + line.len();
+        let before = tokens.iter().take_while(|(offset, _)| *offset < start);
+        let opened: String = open_tags(&before.copied().collect::<Vec<_>>())
+            .iter()
+            .map(|t| t.raw())
+            .collect();
+        let through: Vec<_> = tokens
+            .iter()
+            .take_while(|(offset, _)| *offset < end)
+            .copied()
+            .collect();
+        let balanced = format!("{}{}{}", opened, line, close_tags(&open_tags(&through)));
+
+        let speaker = speaker_lines(&parse_cue_text(&balanced))
+            .into_iter()
+            .next()
+            .and_then(|(speaker, _)| speaker);
+        lines.push((speaker, balanced));
+        start = end + 1;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use core::str::FromStr;
+
+    const MEETING: &str = "WEBVTT
+
+00:00:00.000 --> 00:00:04.000
+<v Ann>Shall we start?</v>
+
+00:00:02.000 --> 00:00:06.000
+<v Ann>I have the agenda
+<i>somewhere</i>.
+
+00:00:06.000 --> 00:00:08.000
+<v Ann>Bob?</v>
+<v.loud Bob>Yes!</v>
+
+00:00:09.000 --> 00:00:10.000
+Applause";
+
+    #[test]
+    fn test_speaker_stats() {
+        let vtt = WebVtt::from_str(MEETING).unwrap();
+        assert_eq!(
+            vtt.speakers(),
+            vec![
+                SpeakerStats {
+                    name: "Ann".to_string(),
+                    cues: 3,
+                    talk_time: Duration::from_secs(8),
+                },
+                SpeakerStats {
+                    name: "Bob".to_string(),
+                    cues: 1,
+                    talk_time: Duration::from_secs(2),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_and_split_speakers() {
+        let vtt = WebVtt::from_str(MEETING).unwrap();
+        let bob = vtt.filter_speaker("Bob");
+        assert_eq!(bob.cues.len(), 1);
+        assert_eq!(bob.cues[0].payload, "<v.loud Bob>Yes!</v>");
+
+        let ann = vtt.filter_speaker("Ann");
+        assert_eq!(ann.cues.len(), 3);
+        assert_eq!(
+            ann.cues[1].payload,
+            "<v Ann>I have the agenda\n<i>somewhere</i>."
+        );
+        assert_eq!(ann.cues[2].payload, "<v Ann>Bob?</v>");
+
+        let split = vtt.split_by_speaker();
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0], ("Ann".to_string(), ann));
+        assert_eq!(split[1], ("Bob".to_string(), bob));
+    }
+
+    #[test]
+    fn test_rename_speaker() {
+        let mut vtt = WebVtt::from_str(MEETING).unwrap();
+        assert_eq!(vtt.rename_speaker("Bob", "Robert & Co"), 1);
+        assert_eq!(
+            vtt.cues[2].payload,
+            "<v Ann>Bob?</v>\n<v.loud Robert &amp; Co>Yes!</v>"
+        );
+        assert_eq!(vtt.cues[2].speakers(), vec!["Ann", "Robert & Co"]);
+        assert_eq!(vtt.rename_speaker("Nobody", "Someone"), 0);
+    }
+}
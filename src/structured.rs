@@ -0,0 +1,545 @@
+//! Structured serde representation of cues and documents.
+//!
+//! The `Serialize` and `Deserialize` implementations of `WebVtt`, `VttCue` and
+//! `VttSettings` use the WebVTT text as a single string. This module maps them
+//! to objects instead, for consumers such as web editors that read captions
+//! from JSON:
+//!
+//! ```json
+//! {
+//!   "cues": [
+//!     {
+//!       "id": "1",
+//!       "start": 1000,
+//!       "end": 5000,
+//!       "settings": { "line": 0, "align": "start" },
+//!       "payload": "Hello, world!"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Wrap a value in `Structured` to serialize it this way, or use the module
+//! with `#[serde(with = "vtt::structured")]` on a field. Deserialization
+//! accepts both time formats and both payload formats.
+
+use crate::markup::{render_cue_text, CueNode, CueSpan, SpanKind};
+use crate::{parse_settings, VttCue, VttParseError, VttSettings, VttTimestamp};
+use crate::{VttHeader, WebVtt};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+use core::time::Duration;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// How cue times are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeFormat {
+    /// Whole milliseconds, e.g. `61500`.
+    #[default]
+    Millis,
+    /// WebVTT timestamps, e.g. `"00:01:01.500"`.
+    Text,
+}
+
+/// How cue payloads are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadFormat {
+    /// The cue text as a string, markup included.
+    #[default]
+    Text,
+    /// The cue text as a tree of nodes, as returned by `parse_cue_text`.
+    Nodes,
+}
+
+/// Options for the structured representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StructuredOptions {
+    /// How cue times are written.
+    pub time: TimeFormat,
+    /// How cue payloads are written.
+    pub payload: PayloadFormat,
+}
+
+/// A value serialized in the structured representation.
+///
+/// Implemented for `WebVtt`, `VttCue` and `VttSettings`, and for references
+/// to them when serializing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structured<T> {
+    /// The wrapped value.
+    pub value: T,
+    /// The options used when serializing. Deserialization ignores them.
+    pub options: StructuredOptions,
+}
+
+impl<T> Structured<T> {
+    /// Wraps a value with the default options: times in milliseconds and
+    /// payloads as text.
+    pub fn new(value: T) -> Self {
+        Self::with_options(value, StructuredOptions::default())
+    }
+
+    /// Wraps a value with the given options.
+    pub fn with_options(value: T, options: StructuredOptions) -> Self {
+        Self { value, options }
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+/// Types that have a structured representation.
+pub trait StructuredRepr: Sized + sealed::Sealed {
+    #[doc(hidden)]
+    type Repr: Serialize + for<'de> Deserialize<'de>;
+
+    #[doc(hidden)]
+    fn to_repr(&self, options: &StructuredOptions) -> Self::Repr;
+
+    #[doc(hidden)]
+    fn from_repr(repr: Self::Repr) -> Result<Self, VttParseError>;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for crate::WebVtt {}
+    impl Sealed for crate::VttCue {}
+    impl Sealed for crate::VttSettings {}
+}
+
+// Implemented per type rather than for every `T: StructuredRepr`, which would
+// conflict with the implementation for references.
+macro_rules! impl_serialize {
+    ($($ty:ty),*) => {$(
+        impl Serialize for Structured<$ty> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                self.value.to_repr(&self.options).serialize(serializer)
+            }
+        }
+
+        impl Serialize for Structured<&$ty> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                self.value.to_repr(&self.options).serialize(serializer)
+            }
+        }
+    )*};
+}
+
+impl_serialize!(WebVtt, VttCue, VttSettings);
+
+impl<'de, T: StructuredRepr> Deserialize<'de> for Structured<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(deserializer).map(Structured::new)
+    }
+}
+
+/// Serializes a value in the structured representation with the default
+/// options, for use with `#[serde(with = "vtt::structured")]`.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: StructuredRepr,
+    S: Serializer,
+{
+    value
+        .to_repr(&StructuredOptions::default())
+        .serialize(serializer)
+}
+
+/// Deserializes a value from the structured representation, for use with
+/// `#[serde(with = "vtt::structured")]`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: StructuredRepr,
+    D: Deserializer<'de>,
+{
+    let repr = T::Repr::deserialize(deserializer)?;
+    T::from_repr(repr).map_err(serde::de::Error::custom)
+}
+
+// The representation types are public so they can appear in `StructuredRepr`,
+// but live in this private module so they can't be named outside the crate.
+mod repr {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct DocumentRepr {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub description: Option<String>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub metadata: BTreeMap<String, String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub styles: Vec<String>,
+        #[serde(default)]
+        pub cues: Vec<CueRepr>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct CueRepr {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub id: Option<String>,
+        pub start: TimeRepr,
+        pub end: TimeRepr,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub settings: Option<SettingsRepr>,
+        pub payload: PayloadRepr,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct SettingsRepr {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub vertical: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub line: Option<LineRepr>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub position: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub size: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub align: Option<String>,
+    }
+
+    /// A line number, or a percentage such as `"50%"` or `"auto"`.
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub enum LineRepr {
+        Number(i32),
+        Text(String),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub enum TimeRepr {
+        Millis(u64),
+        Text(String),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub enum PayloadRepr {
+        Text(String),
+        Nodes(Vec<NodeRepr>),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    pub enum NodeRepr {
+        Text {
+            text: String,
+        },
+        Timestamp {
+            time: TimeRepr,
+        },
+        Span {
+            tag: String,
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            classes: Vec<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            annotation: Option<String>,
+            #[serde(default)]
+            children: Vec<NodeRepr>,
+        },
+    }
+}
+
+use repr::{CueRepr, DocumentRepr, LineRepr, NodeRepr, PayloadRepr, SettingsRepr, TimeRepr};
+
+impl TimeRepr {
+    fn new(timestamp: &VttTimestamp, options: &StructuredOptions) -> Self {
+        match options.time {
+            TimeFormat::Millis => TimeRepr::Millis(timestamp.as_duration().as_millis() as u64),
+            TimeFormat::Text => TimeRepr::Text(timestamp.to_string()),
+        }
+    }
+
+    fn timestamp(&self) -> Result<VttTimestamp, VttParseError> {
+        match self {
+            TimeRepr::Millis(ms) => Ok(VttTimestamp::new(Duration::from_millis(*ms))),
+            TimeRepr::Text(text) => VttTimestamp::from_str(text),
+        }
+    }
+}
+
+impl NodeRepr {
+    fn new(node: &CueNode, options: &StructuredOptions) -> Self {
+        match node {
+            CueNode::Text(text) => NodeRepr::Text { text: text.clone() },
+            CueNode::Timestamp(timestamp) => NodeRepr::Timestamp {
+                time: TimeRepr::new(timestamp, options),
+            },
+            CueNode::Span(span) => NodeRepr::Span {
+                tag: span.kind.tag_name().to_string(),
+                classes: span.classes.clone(),
+                annotation: span.annotation.clone(),
+                children: span
+                    .children
+                    .iter()
+                    .map(|child| NodeRepr::new(child, options))
+                    .collect(),
+            },
+        }
+    }
+
+    fn node(self) -> Result<CueNode, VttParseError> {
+        Ok(match self {
+            NodeRepr::Text { text } => CueNode::Text(text),
+            NodeRepr::Timestamp { time } => CueNode::Timestamp(time.timestamp()?),
+            NodeRepr::Span {
+                tag,
+                classes,
+                annotation,
+                children,
+            } => CueNode::Span(CueSpan {
+                kind: SpanKind::from_tag_name(&tag).ok_or(VttParseError::InvalidFormat)?,
+                classes,
+                annotation,
+                children: children
+                    .into_iter()
+                    .map(NodeRepr::node)
+                    .collect::<Result<_, _>>()?,
+            }),
+        })
+    }
+}
+
+impl StructuredRepr for VttSettings {
+    type Repr = SettingsRepr;
+
+    fn to_repr(&self, _options: &StructuredOptions) -> SettingsRepr {
+        use crate::LineSetting;
+        SettingsRepr {
+            vertical: self.vertical.as_ref().map(ToString::to_string),
+            line: self.line.as_ref().map(|line| match line {
+                LineSetting::Number(n) => LineRepr::Number(*n),
+                other => LineRepr::Text(other.to_string()),
+            }),
+            position: self.position,
+            size: self.size,
+            align: self.align.as_ref().map(ToString::to_string),
+        }
+    }
+
+    fn from_repr(repr: SettingsRepr) -> Result<Self, VttParseError> {
+        // Go through the settings syntax, so the values are validated the same
+        // way as in a cue timing line.
+        let mut settings = Vec::new();
+        if let Some(vertical) = repr.vertical {
+            settings.push(format!("vertical:{}", vertical));
+        }
+        match repr.line {
+            Some(LineRepr::Number(n)) => settings.push(format!("line:{}", n)),
+            Some(LineRepr::Text(text)) => settings.push(format!("line:{}", text)),
+            None => {}
+        }
+        if let Some(position) = repr.position {
+            settings.push(format!("position:{}%", position));
+        }
+        if let Some(size) = repr.size {
+            settings.push(format!("size:{}%", size));
+        }
+        if let Some(align) = repr.align {
+            settings.push(format!("align:{}", align));
+        }
+        if settings
+            .iter()
+            .any(|setting| setting.contains(char::is_whitespace))
+        {
+            return Err(VttParseError::InvalidSetting(settings.join(" ")));
+        }
+        parse_settings(&settings.join(" "))
+    }
+}
+
+impl StructuredRepr for VttCue {
+    type Repr = CueRepr;
+
+    fn to_repr(&self, options: &StructuredOptions) -> CueRepr {
+        let payload = match options.payload {
+            PayloadFormat::Text => PayloadRepr::Text(self.payload.clone()),
+            PayloadFormat::Nodes => PayloadRepr::Nodes(
+                self.nodes()
+                    .iter()
+                    .map(|node| NodeRepr::new(node, options))
+                    .collect(),
+            ),
+        };
+        CueRepr {
+            id: self.identifier.clone(),
+            start: TimeRepr::new(&self.start, options),
+            end: TimeRepr::new(&self.end, options),
+            settings: self
+                .settings
+                .as_ref()
+                .map(|settings| settings.to_repr(options)),
+            payload,
+        }
+    }
+
+    fn from_repr(repr: CueRepr) -> Result<Self, VttParseError> {
+        let payload = match repr.payload {
+            PayloadRepr::Text(text) => text,
+            PayloadRepr::Nodes(nodes) => {
+                let nodes = nodes
+                    .into_iter()
+                    .map(NodeRepr::node)
+                    .collect::<Result<Vec<_>, _>>()?;
+                render_cue_text(&nodes)
+            }
+        };
+        Ok(VttCue {
+            identifier: repr.id,
+            start: repr.start.timestamp()?,
+            end: repr.end.timestamp()?,
+            settings: repr.settings.map(VttSettings::from_repr).transpose()?,
+            payload,
+        })
+    }
+}
+
+impl StructuredRepr for WebVtt {
+    type Repr = DocumentRepr;
+
+    fn to_repr(&self, options: &StructuredOptions) -> DocumentRepr {
+        DocumentRepr {
+            description: self.header.description.clone(),
+            metadata: self
+                .header
+                .metadata
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            styles: self.styles.clone(),
+            cues: self.cues.iter().map(|cue| cue.to_repr(options)).collect(),
+        }
+    }
+
+    fn from_repr(repr: DocumentRepr) -> Result<Self, VttParseError> {
+        Ok(WebVtt {
+            header: VttHeader {
+                description: repr.description,
+                metadata: repr.metadata.into_iter().collect(),
+            },
+            styles: repr.styles,
+            cues: repr
+                .cues
+                .into_iter()
+                .map(VttCue::from_repr)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const VTT: &str = "WEBVTT
+
+1
+00:00:01.000 --> 00:00:02.500 line:0 align:start
+<v Bob>Hello <i>world</i></v>";
+
+    #[test]
+    fn test_serialize_structured() {
+        let vtt = WebVtt::from_str(VTT).unwrap();
+        let value = serde_json::to_value(Structured::new(&vtt)).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "cues": [{
+                    "id": "1",
+                    "start": 1000,
+                    "end": 2500,
+                    "settings": { "line": 0, "align": "start" },
+                    "payload": "<v Bob>Hello <i>world</i></v>",
+                }]
+            })
+        );
+
+        let options = StructuredOptions {
+            time: TimeFormat::Text,
+            payload: PayloadFormat::Nodes,
+        };
+        let value = serde_json::to_value(Structured::with_options(&vtt.cues[0], options)).unwrap();
+        assert_eq!(value["start"], "00:00:01.000");
+        assert_eq!(
+            value["payload"],
+            json!([{
+                "type": "span",
+                "tag": "v",
+                "annotation": "Bob",
+                "children": [
+                    { "type": "text", "text": "Hello " },
+                    {
+                        "type": "span",
+                        "tag": "i",
+                        "children": [{ "type": "text", "text": "world" }],
+                    },
+                ],
+            }])
+        );
+    }
+
+    #[test]
+    fn test_deserialize_structured() {
+        let vtt = WebVtt::from_str(VTT).unwrap();
+        let options = StructuredOptions {
+            time: TimeFormat::Text,
+            payload: PayloadFormat::Nodes,
+        };
+        let text = serde_json::to_string(&Structured::with_options(&vtt, options)).unwrap();
+        let parsed: Structured<WebVtt> = serde_json::from_str(&text).unwrap();
+        assert_eq!(parsed.value, vtt);
+
+        let cue: Structured<VttCue> = serde_json::from_value(json!({
+            "start": 0,
+            "end": "00:00:01.000",
+            "settings": { "line": "50%" },
+            "payload": "a < b",
+        }))
+        .unwrap();
+        assert_eq!(
+            cue.value.to_string(),
+            "00:00:00.000 --> 00:00:01.000 line:50%\na < b"
+        );
+
+        let invalid: Result<Structured<VttCue>, _> = serde_json::from_value(json!({
+            "start": 0,
+            "end": 1000,
+            "settings": { "align": "top" },
+            "payload": "",
+        }));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_serde_with() {
+        #[derive(Serialize, Deserialize)]
+        struct Response {
+            #[serde(with = "crate::structured")]
+            captions: WebVtt,
+        }
+
+        let response = Response {
+            captions: WebVtt::from_str(VTT).unwrap(),
+        };
+        let text = serde_json::to_string(&response).unwrap();
+        assert!(text.starts_with("{\"captions\":{\"cues\":[{\"id\":\"1\",\"start\":1000"));
+        let parsed: Response = serde_json::from_str(&text).unwrap();
+        assert_eq!(parsed.captions, response.captions);
+    }
+}
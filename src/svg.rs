@@ -0,0 +1,513 @@
+//! Rendering of active cues to SVG snapshots.
+//!
+//! Cues are laid out with a `LayoutEngine` and styled from their payload tags
+//! and the `::cue` rules of the file's STYLE blocks. Only simple `::cue` and
+//! `::cue(selector)` rules with type, class, ID and attribute selectors are
+//! supported, with the `color`, `background-color`, `font-family`,
+//! `font-weight`, `font-style` and `text-decoration` properties.
+
+use crate::layout::{CueBox, LayoutEngine, WritingMode};
+use crate::markup::{CueNode, CueSpan, SpanKind};
+use crate::{VttTimestamp, WebVtt};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::ops::Range;
+
+/// Options for `WebVtt::render_svg`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    /// The viewport and font model used to lay out cues.
+    pub engine: LayoutEngine,
+    /// The default font family.
+    pub font_family: String,
+    /// The font size, in pixels.
+    pub font_size: f64,
+    /// The default text color.
+    pub color: String,
+    /// The default background color behind cue text.
+    pub cue_background: String,
+    /// The background color of the frame, or `None` for a transparent frame.
+    pub background: Option<String>,
+}
+
+impl SvgOptions {
+    /// Creates options with white text on a translucent black background.
+    pub fn new(engine: LayoutEngine) -> Self {
+        SvgOptions {
+            engine,
+            font_family: "monospace".to_string(),
+            font_size: engine.metrics.line_height / 1.2,
+            color: "white".to_string(),
+            cue_background: "rgba(0,0,0,0.8)".to_string(),
+            background: Some("black".to_string()),
+        }
+    }
+}
+
+/// The computed style of a run of cue text.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Style {
+    color: Option<String>,
+    background: Option<String>,
+    font_family: Option<String>,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl Style {
+    fn apply(&mut self, declarations: &[(String, String)]) {
+        for (property, value) in declarations {
+            match property.as_str() {
+                "color" => self.color = Some(value.clone()),
+                "background-color" | "background" => self.background = Some(value.clone()),
+                "font-family" => self.font_family = Some(value.clone()),
+                "font-weight" => {
+                    self.bold = matches!(value.as_str(), "bold" | "bolder")
+                        || value.parse::<u32>().is_ok_and(|w| w >= 600)
+                }
+                "font-style" => self.italic = matches!(value.as_str(), "italic" | "oblique"),
+                "text-decoration" | "text-decoration-line" => {
+                    self.underline = value.split_whitespace().any(|v| v == "underline")
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A compound selector inside `::cue(...)`; an empty selector matches the whole cue.
+#[derive(Debug, Default, PartialEq)]
+struct Selector {
+    tag: Option<String>,
+    classes: Vec<String>,
+    id: Option<String>,
+    attributes: Vec<(String, String)>,
+}
+
+impl Selector {
+    fn parse(selector: &str) -> Option<Selector> {
+        let rest = selector.trim().strip_prefix("::cue")?.trim();
+        if rest.is_empty() {
+            return Some(Selector::default());
+        }
+        let inner = rest.strip_prefix('(')?.strip_suffix(')')?.trim();
+        // Combinators are not supported.
+        let head = inner.split('[').next().unwrap_or(inner);
+        if head.contains(|c: char| c.is_whitespace() || matches!(c, '>' | '+' | '~')) {
+            return None;
+        }
+
+        let mut parsed = Selector::default();
+        let is_ident = |c: char| c.is_alphanumeric() || matches!(c, '-' | '_');
+        let mut rest = inner;
+
+        let tag_len = match rest.strip_prefix('*') {
+            Some(_) => 1,
+            None => rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len()),
+        };
+        if tag_len > 0 {
+            parsed.tag = Some(rest[..tag_len].to_string());
+            rest = &rest[tag_len..];
+        }
+
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            if c == '[' {
+                let end = rest.find(']')?;
+                let (name, value) = rest[..end].split_once('=')?;
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                parsed
+                    .attributes
+                    .push((name.trim().to_string(), value.to_string()));
+                rest = &rest[end + 1..];
+                continue;
+            }
+            let len = rest.find(|c: char| !is_ident(c)).unwrap_or(rest.len());
+            if len == 0 {
+                return None;
+            }
+            let ident = rest[..len].to_string();
+            rest = &rest[len..];
+            match c {
+                '.' => parsed.classes.push(ident),
+                '#' => parsed.id = Some(ident),
+                _ => return None,
+            }
+        }
+
+        Some(parsed)
+    }
+
+    fn is_cue(&self) -> bool {
+        self.tag.is_none() && self.classes.is_empty() && self.attributes.is_empty()
+    }
+
+    fn matches_cue(&self, identifier: Option<&str>) -> bool {
+        self.id.is_none() || self.id.as_deref() == identifier
+    }
+
+    fn matches_span(&self, span: &CueSpan) -> bool {
+        let tag_matches = self
+            .tag
+            .as_deref()
+            .is_none_or(|tag| tag == "*" || tag == span.kind.tag_name());
+        let classes_match = self.classes.iter().all(|c| span.classes.contains(c));
+        let attributes_match = self.attributes.iter().all(|(name, value)| {
+            let expected = match (name.as_str(), span.kind) {
+                ("voice", SpanKind::Voice) | ("lang", SpanKind::Language) => {
+                    span.annotation.as_ref()
+                }
+                _ => None,
+            };
+            expected == Some(value)
+        });
+        tag_matches && classes_match && attributes_match
+    }
+}
+
+/// A `::cue` rule from a STYLE block.
+#[derive(Debug)]
+struct Rule {
+    selector: Selector,
+    declarations: Vec<(String, String)>,
+}
+
+/// Parses the supported `::cue` rules from CSS, skipping everything else.
+fn parse_rules(css: &str) -> Vec<Rule> {
+    let mut css = css.to_string();
+    while let Some(start) = css.find("/*") {
+        let end = css[start..].find("*/").map_or(css.len(), |e| start + e + 2);
+        css.replace_range(start..end, "");
+    }
+
+    let mut rules = Vec::new();
+    let mut rest = css.as_str();
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|c| open + c) else {
+            break;
+        };
+        let declarations: Vec<(String, String)> = rest[open + 1..close]
+            .split(';')
+            .filter_map(|d| d.split_once(':'))
+            .map(|(p, v)| {
+                let value = v.trim().trim_end_matches("!important").trim();
+                (p.trim().to_ascii_lowercase(), value.to_string())
+            })
+            .collect();
+        for selector in rest[..open].split(',').filter_map(Selector::parse) {
+            rules.push(Rule {
+                selector,
+                declarations: declarations.clone(),
+            });
+        }
+        rest = &rest[close + 1..];
+    }
+    rules
+}
+
+/// Computes styled runs over the plain text of a cue.
+fn style_runs(
+    nodes: &[CueNode],
+    style: &Style,
+    rules: &[Rule],
+    identifier: Option<&str>,
+    runs: &mut Vec<(Range<usize>, Style)>,
+    offset: &mut usize,
+) {
+    for node in nodes {
+        match node {
+            CueNode::Text(text) => {
+                let len = text.chars().count();
+                let range = *offset..*offset + len;
+                *offset += len;
+                match runs.last_mut() {
+                    Some((last, last_style)) if last.end == range.start && last_style == style => {
+                        last.end = range.end
+                    }
+                    _ => runs.push((range, style.clone())),
+                }
+            }
+            CueNode::Timestamp(_) => {}
+            CueNode::Span(span) => {
+                let mut inner = style.clone();
+                match span.kind {
+                    SpanKind::Bold => inner.bold = true,
+                    SpanKind::Italic => inner.italic = true,
+                    SpanKind::Underline => inner.underline = true,
+                    _ => {}
+                }
+                for rule in rules {
+                    if !rule.selector.is_cue()
+                        && rule.selector.matches_cue(identifier)
+                        && rule.selector.matches_span(span)
+                    {
+                        inner.apply(&rule.declarations);
+                    }
+                }
+                style_runs(&span.children, &inner, rules, identifier, runs, offset);
+            }
+        }
+    }
+}
+
+impl WebVtt {
+    /// Renders the cues active at the given time as an SVG image.
+    pub fn render_svg(&self, time: &VttTimestamp, options: &SvgOptions) -> String {
+        let rules: Vec<Rule> = self
+            .styles
+            .iter()
+            .flat_map(|css| parse_rules(css))
+            .collect();
+        let viewport = options.engine.viewport;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = number(viewport.width),
+            h = number(viewport.height)
+        );
+        if let Some(ref background) = options.background {
+            let _ = writeln!(
+                svg,
+                r#"<rect width="100%" height="100%" fill="{}"/>"#,
+                escape(background)
+            );
+        }
+
+        for cue_box in self.layout_at(time, &options.engine) {
+            let cue = &self.cues[cue_box.cue];
+            let identifier = cue.identifier.as_deref();
+
+            let mut style = Style::default();
+            for rule in &rules {
+                if rule.selector.is_cue() && rule.selector.matches_cue(identifier) {
+                    style.apply(&rule.declarations);
+                }
+            }
+            let mut runs = Vec::new();
+            style_runs(&cue.nodes(), &style, &rules, identifier, &mut runs, &mut 0);
+
+            render_cue(&mut svg, &cue_box, &runs, options);
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn render_cue(
+    svg: &mut String,
+    cue_box: &CueBox,
+    runs: &[(Range<usize>, Style)],
+    options: &SvgOptions,
+) {
+    let char_width = options.engine.metrics.char_width;
+    let vertical = cue_box.writing_mode != WritingMode::Horizontal;
+
+    let _ = writeln!(svg, r#"<g data-cue="{}">"#, cue_box.cue);
+    for line in &cue_box.lines {
+        let segments: Vec<(Range<usize>, &Style)> = runs
+            .iter()
+            .filter_map(|(range, style)| {
+                let start = range.start.max(line.range.start);
+                let end = range.end.min(line.range.end);
+                (start < end).then_some((start..end, style))
+            })
+            .collect();
+
+        // Backgrounds behind each run of text.
+        for (range, style) in &segments {
+            let offset = (range.start - line.range.start) as f64 * char_width;
+            let length = range.len() as f64 * char_width;
+            let (x, y, width, height) = if vertical {
+                (line.x, line.y + offset, line.width, length)
+            } else {
+                (line.x + offset, line.y, length, line.height)
+            };
+            let fill = style
+                .background
+                .as_deref()
+                .unwrap_or(&options.cue_background);
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                number(x),
+                number(y),
+                number(width),
+                number(height),
+                escape(fill)
+            );
+        }
+
+        let (x, y, length, mode) = if vertical {
+            (
+                line.x + line.width / 2.0,
+                line.y,
+                line.height,
+                r#" writing-mode="vertical-rl""#,
+            )
+        } else {
+            (line.x, line.y + line.height / 2.0, line.width, "")
+        };
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}"{} font-family="{}" font-size="{}" dominant-baseline="central" textLength="{}" lengthAdjust="spacingAndGlyphs" xml:space="preserve">"#,
+            number(x),
+            number(y),
+            mode,
+            escape(&options.font_family),
+            number(options.font_size),
+            number(length)
+        );
+
+        let chars: Vec<char> = line.text.chars().collect();
+        for (range, style) in &segments {
+            let text: String = chars[range.start - line.range.start..range.end - line.range.start]
+                .iter()
+                .collect();
+            let _ = write!(
+                svg,
+                r#"<tspan fill="{}""#,
+                escape(style.color.as_deref().unwrap_or(&options.color))
+            );
+            if let Some(ref family) = style.font_family {
+                let _ = write!(svg, r#" font-family="{}""#, escape(family));
+            }
+            if style.bold {
+                svg.push_str(r#" font-weight="bold""#);
+            }
+            if style.italic {
+                svg.push_str(r#" font-style="italic""#);
+            }
+            if style.underline {
+                svg.push_str(r#" text-decoration="underline""#);
+            }
+            let _ = write!(svg, ">{}</tspan>", escape(&text));
+        }
+        svg.push_str("</text>\n");
+    }
+    svg.push_str("</g>\n");
+}
+
+/// Formats a coordinate with at most two decimal places.
+fn number(value: f64) -> String {
+    let rounded = format!("{:.2}", value);
+    rounded
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Escapes text for use in XML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{FontMetrics, Viewport};
+    use crate::test_util::ts;
+    use alloc::vec;
+    use core::str::FromStr;
+
+    fn options() -> SvgOptions {
+        SvgOptions::new(LayoutEngine::new(
+            Viewport::new(640.0, 360.0),
+            FontMetrics::new(10.0, 20.0),
+        ))
+    }
+
+    #[test]
+    fn test_render_styled_cue() {
+        let vtt = WebVtt::from_str(
+            r#"WEBVTT
+
+STYLE
+::cue { background-color: navy; }
+/* Speaker colors */
+::cue(v[voice="Ann"]) { color: yellow; }
+::cue(.loud) { font-weight: bold !important; }
+
+00:00:00.000 --> 00:00:05.000
+<v Ann>Hi <c.loud>you</c> & <i>me</i></v>"#,
+        )
+        .unwrap();
+        let svg = vtt.render_svg(&ts(1_000), &options());
+
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="640" height="360""#)
+        );
+        assert!(svg.contains(r#"<rect x="265" y="340" width="30" height="20" fill="navy"/>"#));
+        assert!(svg.contains(r#"<tspan fill="yellow">Hi </tspan>"#));
+        assert!(svg.contains(r#"<tspan fill="yellow" font-weight="bold">you</tspan>"#));
+        assert!(svg.contains(r#"<tspan fill="yellow"> &amp; </tspan>"#));
+        assert!(svg.contains(r#"<tspan fill="yellow" font-style="italic">me</tspan>"#));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_render_inactive_time() {
+        let vtt = WebVtt::from_str("WEBVTT\n\n00:00:00.000 --> 00:00:01.000\nHello").unwrap();
+        let svg = vtt.render_svg(&ts(2_000), &options());
+        assert!(!svg.contains("<text"));
+    }
+
+    #[test]
+    fn test_selector_parsing() {
+        assert_eq!(Selector::parse("::cue"), Some(Selector::default()));
+        assert_eq!(
+            Selector::parse("::cue(c.a.b)"),
+            Some(Selector {
+                tag: Some("c".to_string()),
+                classes: vec!["a".to_string(), "b".to_string()],
+                ..Selector::default()
+            })
+        );
+        assert_eq!(Selector::parse("::cue(b i)"), None);
+        assert_eq!(Selector::parse("video"), None);
+        assert_eq!(
+            Selector::parse("::cue(.café)"),
+            Some(Selector {
+                classes: vec!["café".to_string()],
+                ..Selector::default()
+            })
+        );
+        assert_eq!(Selector::parse("::cue(v[voice=a]é)"), None);
+        assert_eq!(Selector::parse("::cue(c.)"), None);
+        assert_eq!(Selector::parse("::cue(c:hover)"), None);
+    }
+
+    #[test]
+    fn test_render_non_ascii_selector() {
+        let vtt = WebVtt::from_str(
+            "WEBVTT
+
+STYLE
+::cue(v[voice=a]é) { color: red }
+::cue(.café) { color: blue }
+
+00:00:00.000 --> 00:00:05.000
+<v a><c.café>Hi</c></v>",
+        )
+        .unwrap();
+        let svg = vtt.render_svg(&ts(1_000), &options());
+        assert!(svg.contains(r#"<tspan fill="blue">Hi</tspan>"#));
+        assert!(!svg.contains("red"));
+    }
+}
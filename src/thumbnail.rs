@@ -0,0 +1,243 @@
+//! Thumbnail sprite tracks for scrubbing previews.
+
+use crate::{VttCue, VttParseError, VttTimestamp, WebVtt};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+
+/// A region of a sprite image, written as a media fragment URL such as
+/// `sprite.jpg#xywh=0,0,160,90`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Thumbnail {
+    /// The URL of the image, without the fragment.
+    pub url: String,
+    /// The left edge of the region, in pixels.
+    pub x: u32,
+    /// The top edge of the region, in pixels.
+    pub y: u32,
+    /// The width of the region, in pixels.
+    pub width: u32,
+    /// The height of the region, in pixels.
+    pub height: u32,
+}
+
+impl FromStr for Thumbnail {
+    type Err = VttParseError;
+
+    /// Parses a `Thumbnail` from a URL with an `xywh` media fragment in pixels.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (url, fragment) = s
+            .trim()
+            .split_once('#')
+            .ok_or(VttParseError::InvalidFormat)?;
+        let xywh = fragment
+            .split('&')
+            .find_map(|param| param.strip_prefix("xywh="))
+            .ok_or(VttParseError::InvalidFormat)?;
+        let xywh = xywh.strip_prefix("pixel:").unwrap_or(xywh);
+
+        let values = xywh
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| VttParseError::InvalidFormat)?;
+        let [x, y, width, height] = values[..] else {
+            return Err(VttParseError::InvalidFormat);
+        };
+
+        Ok(Thumbnail {
+            url: url.to_string(),
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+impl fmt::Display for Thumbnail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}#xywh={},{},{},{}",
+            self.url, self.x, self.y, self.width, self.height
+        )
+    }
+}
+
+/// The layout of thumbnail sprite images, for `WebVtt::from_sprites`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteConfig {
+    /// The URL of the sprite images. `{}` is replaced by the number of the
+    /// image, starting from zero, when the thumbnails span several images.
+    pub url: String,
+    /// The time between thumbnails.
+    pub interval: Duration,
+    /// The number of thumbnails per row of an image.
+    pub columns: u32,
+    /// The number of rows of an image.
+    pub rows: u32,
+    /// The width of a thumbnail, in pixels.
+    pub tile_width: u32,
+    /// The height of a thumbnail, in pixels.
+    pub tile_height: u32,
+}
+
+/// The error returned by `WebVtt::from_sprites` when the sprite grid or image
+/// size doesn't fit in `u32`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteGridError(String);
+
+impl fmt::Display for SpriteGridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sprite grid too large: {}", self.0)
+    }
+}
+
+impl Error for SpriteGridError {}
+
+impl WebVtt {
+    /// Creates a thumbnail track covering a video of the given duration.
+    ///
+    /// Thumbnails fill each image row by row before moving on to the next image.
+    /// Returns an empty track if the interval, grid or duration is zero, and
+    /// `SpriteGridError` if the grid or image size doesn't fit in `u32`.
+    pub fn from_sprites(
+        config: &SpriteConfig,
+        duration: Duration,
+    ) -> Result<WebVtt, SpriteGridError> {
+        let too_large = || {
+            SpriteGridError(format!(
+                "{}x{} tiles of {}x{} pixels",
+                config.columns, config.rows, config.tile_width, config.tile_height
+            ))
+        };
+        let per_image = config
+            .columns
+            .checked_mul(config.rows)
+            .ok_or_else(too_large)?;
+        config
+            .columns
+            .checked_mul(config.tile_width)
+            .ok_or_else(too_large)?;
+        config
+            .rows
+            .checked_mul(config.tile_height)
+            .ok_or_else(too_large)?;
+
+        let mut vtt = WebVtt::new();
+        if config.interval.is_zero() || per_image == 0 {
+            return Ok(vtt);
+        }
+
+        let mut start = Duration::ZERO;
+        let mut tile: u64 = 0;
+        while start < duration {
+            let end = (start + config.interval).min(duration);
+            // The remainder is below `per_image`, so it fits in `u32`, and the
+            // coordinates are within the image size checked above.
+            let index = (tile % u64::from(per_image)) as u32;
+            let thumbnail = Thumbnail {
+                url: config
+                    .url
+                    .replace("{}", &(tile / u64::from(per_image)).to_string()),
+                x: index % config.columns * config.tile_width,
+                y: index / config.columns * config.tile_height,
+                width: config.tile_width,
+                height: config.tile_height,
+            };
+            vtt.add_cue(VttCue {
+                identifier: None,
+                start: VttTimestamp::new(start),
+                end: VttTimestamp::new(end),
+                settings: None,
+                payload: thumbnail.to_string(),
+            });
+            start = end;
+            tile += 1;
+        }
+        Ok(vtt)
+    }
+
+    /// Parses the payloads of a thumbnail track, returning one thumbnail per cue
+    /// in cue order.
+    pub fn thumbnails(&self) -> Result<Vec<Thumbnail>, VttParseError> {
+        self.cues
+            .iter()
+            .map(|cue| Thumbnail::from_str(&cue.payload))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thumbnail() {
+        let thumbnail = Thumbnail::from_str("https://cdn/s.jpg#xywh=pixel:160,90,160,90").unwrap();
+        assert_eq!(thumbnail.url, "https://cdn/s.jpg");
+        assert_eq!(
+            (thumbnail.x, thumbnail.y, thumbnail.width, thumbnail.height),
+            (160, 90, 160, 90)
+        );
+        assert_eq!(
+            thumbnail.to_string(),
+            "https://cdn/s.jpg#xywh=160,90,160,90"
+        );
+
+        assert!(Thumbnail::from_str("s.jpg").is_err());
+        assert!(Thumbnail::from_str("s.jpg#xywh=percent:0,0,50,50").is_err());
+        assert!(Thumbnail::from_str("s.jpg#xywh=0,0,160").is_err());
+    }
+
+    #[test]
+    fn test_sprite_track() {
+        let config = SpriteConfig {
+            url: "sprite-{}.jpg".to_string(),
+            interval: Duration::from_secs(10),
+            columns: 2,
+            rows: 2,
+            tile_width: 160,
+            tile_height: 90,
+        };
+        let vtt = WebVtt::from_sprites(&config, Duration::from_secs(45)).unwrap();
+        let text = vtt.to_string();
+        assert!(text.contains("00:00:30.000 --> 00:00:40.000\nsprite-0.jpg#xywh=160,90,160,90"));
+        assert!(text.contains("00:00:40.000 --> 00:00:45.000\nsprite-1.jpg#xywh=0,0,160,90"));
+
+        let parsed = WebVtt::from_str(&text).unwrap().thumbnails().unwrap();
+        assert_eq!(parsed.len(), 5);
+        assert_eq!(parsed[1].x, 160);
+        assert_eq!(parsed[2].y, 90);
+    }
+
+    #[test]
+    fn test_sprite_grid_overflow() {
+        let config = SpriteConfig {
+            url: "sprite.jpg".to_string(),
+            interval: Duration::from_secs(10),
+            columns: 70_000,
+            rows: 70_000,
+            tile_width: 1,
+            tile_height: 1,
+        };
+        let error = WebVtt::from_sprites(&config, Duration::from_secs(60)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "sprite grid too large: 70000x70000 tiles of 1x1 pixels"
+        );
+
+        let config = SpriteConfig {
+            columns: 10,
+            rows: 1,
+            tile_width: u32::MAX / 5,
+            ..config
+        };
+        assert!(WebVtt::from_sprites(&config, Duration::from_secs(60)).is_err());
+    }
+}
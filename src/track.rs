@@ -0,0 +1,217 @@
+//! Typed views of chapter and metadata tracks.
+
+use crate::markup::{escape_text, plain_text};
+use crate::{VttCue, VttTimestamp, WebVtt};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+/// A chapter of a `kind="chapters"` track.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    /// The title of the chapter.
+    pub title: String,
+    /// The start time of the chapter.
+    pub start: VttTimestamp,
+    /// The end time of the chapter.
+    pub end: VttTimestamp,
+    /// The chapters nested within this one.
+    pub children: Vec<Chapter>,
+}
+
+impl Chapter {
+    /// Creates a new `Chapter` without nested chapters.
+    pub fn new(title: &str, start: VttTimestamp, end: VttTimestamp) -> Self {
+        Chapter {
+            title: title.to_string(),
+            start,
+            end,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// A cue of a `kind="metadata"` track with a JSON payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataCue<T> {
+    /// The identifier of the cue.
+    pub identifier: Option<String>,
+    /// The start time of the cue.
+    pub start: VttTimestamp,
+    /// The end time of the cue.
+    pub end: VttTimestamp,
+    /// The deserialized payload.
+    pub data: T,
+}
+
+impl WebVtt {
+    /// Returns the cues as a list of chapters.
+    ///
+    /// A cue lying within the time range of an earlier cue is nested in it.
+    /// Titles are the payload text without markup.
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let mut cues: Vec<&VttCue> = self.cues.iter().collect();
+        cues.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+        // The chapters still open, outermost first.
+        let mut open: Vec<Chapter> = Vec::new();
+        let mut chapters = Vec::new();
+
+        fn close(open: &mut Vec<Chapter>, chapters: &mut Vec<Chapter>) {
+            if let Some(chapter) = open.pop() {
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(chapter),
+                    None => chapters.push(chapter),
+                }
+            }
+        }
+
+        for cue in cues {
+            while open.last().is_some_and(|parent| cue.end > parent.end) {
+                close(&mut open, &mut chapters);
+            }
+            let title = plain_text(&cue.payload).trim().to_string();
+            open.push(Chapter::new(&title, cue.start, cue.end));
+        }
+        while !open.is_empty() {
+            close(&mut open, &mut chapters);
+        }
+        chapters
+    }
+
+    /// Creates a chapter track from a list of chapters, nested chapters
+    /// following their parent.
+    pub fn from_chapters(chapters: &[Chapter]) -> WebVtt {
+        fn push(chapters: &[Chapter], cues: &mut Vec<VttCue>) {
+            for chapter in chapters {
+                cues.push(VttCue {
+                    identifier: None,
+                    start: chapter.start,
+                    end: chapter.end,
+                    settings: None,
+                    payload: escape_text(&chapter.title),
+                });
+                push(&chapter.children, cues);
+            }
+        }
+
+        let mut vtt = WebVtt::new();
+        push(chapters, &mut vtt.cues);
+        vtt
+    }
+
+    /// Deserializes the JSON payloads of a metadata track.
+    #[cfg(feature = "json")]
+    pub fn metadata<T: DeserializeOwned>(&self) -> Result<Vec<MetadataCue<T>>, serde_json::Error> {
+        self.cues
+            .iter()
+            .map(|cue| {
+                Ok(MetadataCue {
+                    identifier: cue.identifier.clone(),
+                    start: cue.start,
+                    end: cue.end,
+                    data: serde_json::from_str(&cue.payload)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Creates a metadata track with JSON payloads.
+    #[cfg(feature = "json")]
+    pub fn from_metadata<T: Serialize>(
+        cues: &[MetadataCue<T>],
+    ) -> Result<WebVtt, serde_json::Error> {
+        let mut vtt = WebVtt::new();
+        for cue in cues {
+            // Compact JSON has no line breaks, and "-->" can only occur within
+            // strings, where it is escaped so it cannot be read as a timing line.
+            let payload = serde_json::to_string(&cue.data)?.replace("-->", "--\\u003e");
+            vtt.add_cue(VttCue {
+                identifier: cue.identifier.clone(),
+                start: cue.start,
+                end: cue.end,
+                settings: None,
+                payload,
+            });
+        }
+        Ok(vtt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ts;
+    use alloc::vec;
+    use core::str::FromStr;
+    #[cfg(feature = "json")]
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn test_nested_chapters() {
+        let vtt = WebVtt::from_str(
+            "WEBVTT
+
+00:00:00.000 --> 00:10:00.000
+Part 1
+
+00:00:00.000 --> 00:05:00.000
+Intro &amp; Setup
+
+00:05:00.000 --> 00:10:00.000
+Main
+
+00:10:00.000 --> 00:20:00.000
+Part 2",
+        )
+        .unwrap();
+
+        let mut part1 = Chapter::new("Part 1", ts(0), ts(600_000));
+        part1.children = vec![
+            Chapter::new("Intro & Setup", ts(0), ts(300_000)),
+            Chapter::new("Main", ts(300_000), ts(600_000)),
+        ];
+        let part2 = Chapter::new("Part 2", ts(600_000), ts(1_200_000));
+        let chapters = vec![part1, part2];
+        assert_eq!(vtt.chapters(), chapters);
+        assert_eq!(WebVtt::from_chapters(&chapters), vtt);
+    }
+
+    #[cfg(feature = "json")]
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Scene {
+        name: String,
+        score: u32,
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_metadata_round_trip() {
+        let cues = vec![MetadataCue {
+            identifier: Some("scene-1".to_string()),
+            start: ts(1_000),
+            end: ts(2_000),
+            data: Scene {
+                name: "a --> b".to_string(),
+                score: 7,
+            },
+        }];
+        let vtt = WebVtt::from_metadata(&cues).unwrap();
+        let parsed = WebVtt::from_str(&vtt.to_string()).unwrap();
+        assert_eq!(
+            parsed.cues[0].payload,
+            r#"{"name":"a --\u003e b","score":7}"#
+        );
+        assert_eq!(parsed.metadata::<Scene>().unwrap(), cues);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_invalid_metadata() {
+        let vtt = WebVtt::from_str("WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nnot json").unwrap();
+        assert!(vtt.metadata::<Scene>().is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//! Export of cues as a readable transcript.
+
+use crate::markup::speaker_lines;
+use crate::{VttCue, WebVtt};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// Options for `WebVtt::to_transcript`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptConfig {
+    /// Prefix each paragraph with the start time of its first cue, as `[hh:mm:ss]`.
+    pub timestamps: bool,
+    /// Prefix each paragraph with the speaker named in its `<v>` tag.
+    pub speakers: bool,
+    /// Drop lines repeated from the previous cue, as in roll-up captions.
+    pub merge_duplicates: bool,
+    /// Start a new paragraph after a pause of at least this length, or never
+    /// when `None`. A change of speaker always starts a new paragraph.
+    pub paragraph_gap: Option<Duration>,
+}
+
+impl Default for TranscriptConfig {
+    fn default() -> Self {
+        TranscriptConfig {
+            timestamps: false,
+            speakers: false,
+            merge_duplicates: true,
+            paragraph_gap: Some(Duration::from_secs(2)),
+        }
+    }
+}
+
+/// A paragraph of the transcript being built.
+struct Paragraph {
+    start: Duration,
+    speaker: Option<String>,
+    text: String,
+}
+
+impl WebVtt {
+    /// Exports the cues as a plain-text transcript.
+    ///
+    /// Markup is stripped and the lines of each paragraph are joined into
+    /// running text. Paragraphs are separated by blank lines.
+    pub fn to_transcript(&self, config: &TranscriptConfig) -> String {
+        let mut order: Vec<&VttCue> = self.cues.iter().collect();
+        order.sort_by_key(|cue| cue.start);
+
+        let mut paragraphs: Vec<Paragraph> = Vec::new();
+        let mut previous_lines: Vec<String> = Vec::new();
+        let mut previous_end: Option<Duration> = None;
+
+        for cue in order {
+            let lines = speaker_lines(&cue.nodes());
+            let paused = match (config.paragraph_gap, previous_end) {
+                (Some(gap), Some(end)) => cue.start.as_duration().saturating_sub(end) >= gap,
+                _ => false,
+            };
+            previous_end = Some(
+                previous_end.map_or(cue.end.as_duration(), |end| end.max(cue.end.as_duration())),
+            );
+
+            let mut new_paragraph = paused;
+            for (speaker, text) in &lines {
+                if config.merge_duplicates && previous_lines.contains(text) {
+                    continue;
+                }
+                let speaker_changed = speaker.is_some()
+                    && paragraphs
+                        .last()
+                        .is_some_and(|p| p.speaker.is_some() && p.speaker != *speaker);
+                match paragraphs.last_mut() {
+                    Some(paragraph) if !new_paragraph && !speaker_changed => {
+                        paragraph.text.push(' ');
+                        paragraph.text.push_str(text);
+                        if paragraph.speaker.is_none() {
+                            paragraph.speaker = speaker.clone();
+                        }
+                    }
+                    _ => paragraphs.push(Paragraph {
+                        start: cue.start.as_duration(),
+                        speaker: speaker.clone(),
+                        text: text.clone(),
+                    }),
+                }
+                new_paragraph = false;
+            }
+            previous_lines = lines.into_iter().map(|(_, text)| text).collect();
+        }
+
+        let mut transcript = String::new();
+        for (i, paragraph) in paragraphs.iter().enumerate() {
+            if i > 0 {
+                transcript.push('\n');
+            }
+            if config.timestamps {
+                let secs = paragraph.start.as_secs();
+                transcript.push_str(&format!(
+                    "[{:02}:{:02}:{:02}] ",
+                    secs / 3600,
+                    (secs / 60) % 60,
+                    secs % 60
+                ));
+            }
+            if let (true, Some(speaker)) = (config.speakers, &paragraph.speaker) {
+                transcript.push_str(&format!("{}: ", speaker));
+            }
+            transcript.push_str(&paragraph.text);
+            transcript.push('\n');
+        }
+        transcript
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::FromStr;
+
+    const DIALOGUE: &str = "WEBVTT
+
+00:00:01.000 --> 00:00:03.000
+<v Ann>Hello <i>there</i>,
+how are you?</v>
+
+00:00:03.000 --> 00:00:05.000
+<v Bob>Fine, thanks.
+
+00:00:08.000 --> 00:00:10.000
+<v Bob>Shall we start?";
+
+    #[test]
+    fn test_plain_transcript() {
+        let vtt = WebVtt::from_str(DIALOGUE).unwrap();
+        assert_eq!(
+            vtt.to_transcript(&TranscriptConfig::default()),
+            "Hello there, how are you?\n\nFine, thanks.\n\nShall we start?\n"
+        );
+    }
+
+    #[test]
+    fn test_transcript_prefixes() {
+        let vtt = WebVtt::from_str(DIALOGUE).unwrap();
+        let config = TranscriptConfig {
+            timestamps: true,
+            speakers: true,
+            paragraph_gap: None,
+            ..TranscriptConfig::default()
+        };
+        assert_eq!(
+            vtt.to_transcript(&config),
+            "[00:00:01] Ann: Hello there, how are you?\n\n[00:00:03] Bob: Fine, thanks. Shall we start?\n"
+        );
+    }
+
+    #[test]
+    fn test_transcript_rolling_duplicates() {
+        let vtt = WebVtt::from_str(
+            "WEBVTT
+
+00:00:01.000 --> 00:00:02.000
+one
+
+00:00:02.000 --> 00:00:03.000
+one
+two
+
+00:00:03.000 --> 00:00:04.000
+two
+three",
+        )
+        .unwrap();
+        assert_eq!(
+            vtt.to_transcript(&TranscriptConfig::default()),
+            "one two three\n"
+        );
+
+        let config = TranscriptConfig {
+            merge_duplicates: false,
+            ..TranscriptConfig::default()
+        };
+        assert_eq!(vtt.to_transcript(&config), "one one two two three\n");
+    }
+}
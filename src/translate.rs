@@ -0,0 +1,632 @@
+//! Export of cue text for translation as XLIFF 2.0 or gettext PO, and
+//! re-import of the translations.
+//!
+//! Translation units are keyed by the cue identifier. Cues without an
+//! identifier, or sharing theirs with another cue, are keyed by `#` and the cue
+//! number counting from one, appended to the identifier if there is one. The
+//! cue timing is given in a note for the translator.
+
+use crate::line_ending::lines;
+use crate::{VttParseError, WebVtt};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl WebVtt {
+    /// Exports the cue payloads as an XLIFF 2.0 document.
+    pub fn to_xliff(&self, source_lang: &str, target_lang: &str) -> String {
+        let mut xliff = String::new();
+        xliff.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xliff.push_str(&format!(
+            "<xliff xmlns=\"urn:oasis:names:tc:xliff:document:2.0\" version=\"2.0\" srcLang=\"{}\" trgLang=\"{}\">\n",
+            escape_xml(source_lang),
+            escape_xml(target_lang)
+        ));
+        xliff.push_str("  <file id=\"f1\">\n");
+        for (key, i) in self.unit_keys() {
+            let cue = &self.cues[i];
+            xliff.push_str(&format!("    <unit id=\"{}\">\n", escape_xml(&key)));
+            xliff.push_str(&format!(
+                "      <notes>\n        <note category=\"timing\">{} --&gt; {}</note>\n      </notes>\n",
+                cue.start, cue.end
+            ));
+            xliff.push_str(&format!(
+                "      <segment>\n        <source xml:space=\"preserve\">{}</source>\n      </segment>\n",
+                escape_xml(cue.payload.trim())
+            ));
+            xliff.push_str("    </unit>\n");
+        }
+        xliff.push_str("  </file>\n</xliff>\n");
+        xliff
+    }
+
+    /// Exports the cue payloads as a gettext PO file for the given language.
+    pub fn to_po(&self, language: &str) -> String {
+        let mut po = String::new();
+        po.push_str("msgid \"\"\nmsgstr \"\"\n");
+        po.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+        po.push_str(&format!("\"Language: {}\\n\"\n", escape_po(language)));
+        for (key, i) in self.unit_keys() {
+            let cue = &self.cues[i];
+            po.push_str(&format!("\n#. {} --> {}\n", cue.start, cue.end));
+            po.push_str(&format!("msgctxt \"{}\"\n", escape_po(&key)));
+            po.push_str(&format!("msgid {}\n", po_string(cue.payload.trim())));
+            po.push_str("msgstr \"\"\n");
+        }
+        po
+    }
+
+    /// Returns a copy of the file with the payloads replaced by the targets of
+    /// a translated XLIFF 2.0 document.
+    ///
+    /// Cues without a translated unit keep their payload, and units matching no
+    /// cue are ignored. Timing and settings are always kept.
+    ///
+    /// Targets must be plain text, escaped or in CDATA sections; comments and
+    /// processing instructions are skipped. Inline elements in targets and
+    /// document type declarations aren't supported and fail with
+    /// `VttParseError::InvalidTranslation`.
+    pub fn apply_xliff(&self, xliff: &str) -> Result<WebVtt, VttParseError> {
+        let mut translations = BTreeMap::new();
+        // The id of the current unit, and its target text if it has one. A
+        // unit may be split into several segments, each with a target.
+        let mut unit: Option<(String, Option<String>)> = None;
+        let mut in_target = false;
+
+        for token in xml_tokens(xliff)? {
+            match token {
+                XmlToken::End("target") => in_target = false,
+                XmlToken::Start { name, .. } | XmlToken::End(name) if in_target => {
+                    return Err(invalid_translation(format!(
+                        "unsupported element <{}> in a target",
+                        name
+                    )));
+                }
+                XmlToken::Text(text) if in_target => {
+                    if let Some((_, Some(target))) = &mut unit {
+                        target.push_str(&unescape_xml(text));
+                    }
+                }
+                XmlToken::CData(text) if in_target => {
+                    if let Some((_, Some(target))) = &mut unit {
+                        target.push_str(text);
+                    }
+                }
+                XmlToken::Start {
+                    name: "unit",
+                    attributes,
+                    ..
+                } => {
+                    let id = attribute(attributes, "id")
+                        .ok_or_else(|| invalid_translation("unit without id".to_string()))?;
+                    unit = Some((id, None));
+                }
+                XmlToken::End("unit") => {
+                    if let Some((id, Some(target))) = unit.take() {
+                        translations.insert(id, target);
+                    }
+                }
+                XmlToken::Start {
+                    name: "target",
+                    empty: false,
+                    ..
+                } => {
+                    if let Some((_, target)) = &mut unit {
+                        target.get_or_insert_with(String::new);
+                        in_target = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if in_target || unit.is_some() {
+            return Err(invalid_translation("unterminated unit".to_string()));
+        }
+        Ok(self.with_translations(&translations))
+    }
+
+    /// Returns a copy of the file with the payloads replaced by the
+    /// translations of a PO file.
+    ///
+    /// Entries with an empty `msgstr` or marked fuzzy are treated as
+    /// untranslated. Entries matching no cue are ignored. Timing and settings
+    /// are always kept.
+    pub fn apply_po(&self, po: &str) -> Result<WebVtt, VttParseError> {
+        let mut translations = BTreeMap::new();
+
+        // Entries are separated by blank lines, whatever the line endings.
+        // Lines are kept with their numbers for error messages.
+        let mut entries = vec![Vec::new()];
+        for (number, line) in lines(po).map(str::trim).enumerate() {
+            match entries.last_mut() {
+                Some(entry) if !line.is_empty() => entry.push((number + 1, line)),
+                _ => entries.push(Vec::new()),
+            }
+        }
+
+        for entry in entries {
+            let mut fuzzy = false;
+            let (mut context, mut msgid, mut msgstr): (Option<String>, _, _) = (None, None, None);
+            // The field that continuation strings are appended to.
+            let mut field: Option<&str> = None;
+
+            for (number, line) in entry {
+                if line.starts_with("#,") && line.contains("fuzzy") {
+                    fuzzy = true;
+                    continue;
+                } else if line.starts_with('#') {
+                    continue;
+                }
+
+                let (name, value) = match line.split_once(' ') {
+                    Some((name, value)) if !line.starts_with('"') => (name, value),
+                    _ => match field {
+                        Some(name) => (name, line),
+                        None => {
+                            return Err(invalid_translation(format!(
+                                "line {}: string outside a field",
+                                number
+                            )))
+                        }
+                    },
+                };
+                let target = match name {
+                    "msgctxt" => &mut context,
+                    "msgid" => &mut msgid,
+                    "msgstr" => &mut msgstr,
+                    _ => {
+                        return Err(invalid_translation(format!(
+                            "line {}: unsupported keyword `{}`",
+                            number, name
+                        )))
+                    }
+                };
+                let value = parse_po_string(value).map_err(|message| {
+                    invalid_translation(format!("line {}: {} in {}", number, message, name))
+                })?;
+                match target {
+                    Some(text) if field == Some(name) => text.push_str(&value),
+                    _ => *target = Some(value),
+                }
+                field = Some(name);
+            }
+
+            if let (Some(key), Some(text), false) = (context, msgstr, fuzzy) {
+                if !text.is_empty() {
+                    translations.insert(key, text);
+                }
+            }
+        }
+        Ok(self.with_translations(&translations))
+    }
+
+    /// Returns the translation unit key of each cue with the cue index.
+    ///
+    /// Keys are unique, so no two cues share a unit.
+    fn unit_keys(&self) -> Vec<(String, usize)> {
+        let mut identifiers = BTreeMap::new();
+        for id in self.cues.iter().filter_map(|cue| cue.identifier.as_deref()) {
+            *identifiers.entry(id).or_insert(0) += 1;
+        }
+
+        let mut used = BTreeSet::new();
+        self.cues
+            .iter()
+            .enumerate()
+            .map(|(i, cue)| {
+                let mut key = match cue.identifier.as_deref() {
+                    Some(id) if identifiers[id] == 1 => id.to_string(),
+                    id => format!("{}#{}", id.unwrap_or_default(), i + 1),
+                };
+                // A generated key may still equal an identifier such as `#2`.
+                while identifiers.contains_key(key.as_str())
+                    && cue.identifier.as_ref() != Some(&key)
+                    || used.contains(&key)
+                {
+                    key.push_str(&format!("#{}", i + 1));
+                }
+                used.insert(key.clone());
+                (key, i)
+            })
+            .collect()
+    }
+
+    fn with_translations(&self, translations: &BTreeMap<String, String>) -> WebVtt {
+        let mut vtt = self.clone();
+        for (key, i) in self.unit_keys() {
+            if let Some(text) = translations.get(&key) {
+                // Blank lines and arrows would end the cue or start a new one.
+                // Any line ending becomes `\n`, since a lone `\r` would be
+                // read back as a line break.
+                vtt.cues[i].payload = lines(text)
+                    .filter(|line| !line.trim().is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .replace("-->", "--&gt;");
+            }
+        }
+        vtt
+    }
+}
+
+/// A piece of an XML document.
+#[derive(Debug, PartialEq)]
+enum XmlToken<'a> {
+    /// A start tag with its name and attributes, or an empty-element tag.
+    Start {
+        name: &'a str,
+        attributes: &'a str,
+        empty: bool,
+    },
+    /// An end tag with its name.
+    End(&'a str),
+    /// Character data, still escaped.
+    Text(&'a str),
+    /// The content of a CDATA section.
+    CData(&'a str),
+}
+
+/// Splits an XML document into tags and text.
+///
+/// Comments, processing instructions and the XML declaration are skipped.
+/// Document type declarations are rejected, since the entities they may
+/// declare aren't supported.
+fn xml_tokens(xml: &str) -> Result<Vec<XmlToken<'_>>, VttParseError> {
+    let mut tokens = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        if open > 0 {
+            tokens.push(XmlToken::Text(&rest[..open]));
+        }
+        rest = &rest[open..];
+
+        let skip_past = |rest: &str, start: usize, end: &str| {
+            rest[start..]
+                .find(end)
+                .map(|i| start + i + end.len())
+                .ok_or_else(|| invalid_translation(format!("missing `{}`", end)))
+        };
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_past(rest, 4, "-->")?..];
+        } else if rest.starts_with("<?") {
+            rest = &rest[skip_past(rest, 2, "?>")?..];
+        } else if rest.starts_with("<![CDATA[") {
+            let end = skip_past(rest, 9, "]]>")?;
+            tokens.push(XmlToken::CData(&rest[9..end - 3]));
+            rest = &rest[end..];
+        } else if rest.starts_with("<!") {
+            return Err(invalid_translation(
+                "document type declarations are not supported".to_string(),
+            ));
+        } else {
+            // The tag ends at the first `>` outside a quoted attribute value.
+            let mut quote = None;
+            let end = rest
+                .char_indices()
+                .find(|&(_, c)| match quote {
+                    Some(q) => {
+                        if c == q {
+                            quote = None;
+                        }
+                        false
+                    }
+                    None => {
+                        if c == '"' || c == '\'' {
+                            quote = Some(c);
+                        }
+                        c == '>'
+                    }
+                })
+                .map(|(i, _)| i)
+                .ok_or_else(|| invalid_translation("unterminated tag".to_string()))?;
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            if let Some(name) = tag.strip_prefix('/') {
+                tokens.push(XmlToken::End(name.trim()));
+            } else {
+                let (tag, empty) = match tag.strip_suffix('/') {
+                    Some(tag) => (tag, true),
+                    None => (tag, false),
+                };
+                let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+                tokens.push(XmlToken::Start {
+                    name: &tag[..name_end],
+                    attributes: &tag[name_end..],
+                    empty,
+                });
+            }
+        }
+    }
+    if !rest.is_empty() {
+        tokens.push(XmlToken::Text(rest));
+    }
+    Ok(tokens)
+}
+
+/// Returns the unescaped value of an attribute of a start tag.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes.trim_start();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let end = value[1..].find(quote)? + 1;
+        if key == name {
+            return Some(unescape_xml(&value[1..end]));
+        }
+        rest = value[end + 1..].trim_start();
+    }
+    None
+}
+
+fn invalid_translation(message: String) -> VttParseError {
+    VttParseError::InvalidTranslation(message)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        unescaped.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest.find(';').and_then(|semi| {
+            let c = match &rest[1..semi] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                name => {
+                    let number = name.strip_prefix('#')?;
+                    let code = match number.strip_prefix('x') {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)
+                }
+            };
+            c.map(|c| (c, semi))
+        });
+        match entity {
+            Some((c, semi)) => {
+                unescaped.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+fn escape_po(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+/// Formats text as a PO string, splitting it after line breaks.
+fn po_string(text: &str) -> String {
+    if !text.contains('\n') {
+        return format!("\"{}\"", escape_po(text));
+    }
+    let mut po = String::from("\"\"");
+    for line in text.split_inclusive('\n') {
+        po.push_str(&format!("\n\"{}\"", escape_po(line)));
+    }
+    po
+}
+
+/// Parses a quoted PO string, describing what is wrong with it on failure.
+fn parse_po_string(value: &str) -> Result<String, String> {
+    let inner = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| "unquoted string".to_string())?;
+    let mut parsed = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            parsed.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => parsed.push('\n'),
+            Some('t') => parsed.push('\t'),
+            Some('r') => parsed.push('\r'),
+            Some(c @ ('\\' | '"')) => parsed.push(c),
+            Some(c) => return Err(format!("unsupported escape `\\{}`", c)),
+            None => return Err("unterminated escape".to_string()),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::FromStr;
+
+    fn original() -> WebVtt {
+        WebVtt::from_str(
+            "WEBVTT
+
+intro
+00:00:01.000 --> 00:00:02.000 align:start
+<i>Hello</i> & \"welcome\"
+
+00:00:03.000 --> 00:00:04.000
+Two
+lines",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_xliff_round_trip() {
+        let vtt = original();
+        let xliff = vtt.to_xliff("en", "de");
+        assert!(xliff.contains("<unit id=\"intro\">"));
+        assert!(xliff.contains("<note category=\"timing\">00:00:01.000 --&gt; 00:00:02.000</note>"));
+        assert!(xliff.contains(
+            "<source xml:space=\"preserve\">&lt;i&gt;Hello&lt;/i&gt; &amp; &quot;welcome&quot;</source>"
+        ));
+
+        let translated = xliff.replace(
+            "<source xml:space=\"preserve\">Two\nlines</source>",
+            "<source xml:space=\"preserve\">Two\nlines</source>\n        <target>Zwei\nZeilen</target>",
+        );
+        let result = vtt.apply_xliff(&translated).unwrap();
+        assert_eq!(result.cues[0], vtt.cues[0]);
+        assert_eq!(result.cues[1].payload, "Zwei\nZeilen");
+        assert_eq!(result.cues[1].start, vtt.cues[1].start);
+    }
+
+    #[test]
+    fn test_po_round_trip() {
+        let vtt = original();
+        let po = vtt.to_po("de");
+        assert!(po.contains(
+            "#. 00:00:03.000 --> 00:00:04.000\nmsgctxt \"#2\"\nmsgid \"\"\n\"Two\\n\"\n\"lines\"\nmsgstr \"\"\n"
+        ));
+
+        let translated = po
+            .replace(
+                "msgid \"<i>Hello</i> & \\\"welcome\\\"\"\nmsgstr \"\"",
+                "msgid \"<i>Hello</i> & \\\"welcome\\\"\"\nmsgstr \"<i>Hallo</i> & \\\"willkommen\\\"\"",
+            )
+            .replace(
+                "\"lines\"\nmsgstr \"\"",
+                "\"lines\"\nmsgstr \"\"\n\"Zwei\\n\"\n\"Zeilen\"",
+            );
+        let result = vtt.apply_po(&translated).unwrap();
+        assert_eq!(result.cues[0].payload, "<i>Hallo</i> & \"willkommen\"");
+        assert_eq!(result.cues[0].settings, vtt.cues[0].settings);
+        assert_eq!(result.cues[1].payload, "Zwei\nZeilen");
+    }
+
+    #[test]
+    fn test_po_crlf() {
+        let vtt = original();
+        let po = "msgid \"\"\r\nmsgstr \"\"\r\n\r\nmsgctxt \"intro\"\r\nmsgid \"Hello\"\r\nmsgstr \"Hallo\"\r\n\r\nmsgctxt \"#2\"\r\nmsgid \"\"\r\n\"Two\\n\"\r\n\"lines\"\r\nmsgstr \"\"\r\n\"Zwei\\n\"\r\n\"Zeilen\"\r\n";
+        let result = vtt.apply_po(po).unwrap();
+        assert_eq!(result.cues[0].payload, "Hallo");
+        assert_eq!(result.cues[1].payload, "Zwei\nZeilen");
+    }
+
+    #[test]
+    fn test_po_fuzzy_and_invalid() {
+        let vtt = original();
+        let po = "#, fuzzy\nmsgctxt \"#2\"\nmsgid \"Two\"\nmsgstr \"Zwei\"\n";
+        assert_eq!(vtt.apply_po(po).unwrap(), vtt);
+        assert!(vtt.apply_po("msgid \"unterminated\n").is_err());
+
+        let po = "msgctxt \"intro\"\nmsgid \"Hello\"\nmsgstr \"a\\qb\"\n";
+        assert_eq!(
+            vtt.apply_po(po).unwrap_err().to_string(),
+            "Invalid translation file: line 3: unsupported escape `\\q` in msgstr"
+        );
+    }
+
+    #[test]
+    fn test_po_carriage_returns() {
+        let vtt = original();
+        let po = "msgctxt \"intro\"\nmsgid \"Hello\"\nmsgstr \"Hallo\\rWelt\"\n\nmsgctxt \"#2\"\nmsgid \"Two\"\nmsgstr \"Zwei\\r\\nZeilen\"\n";
+        let result = vtt.apply_po(po).unwrap();
+        assert_eq!(result.cues[0].payload, "Hallo\nWelt");
+        assert_eq!(result.cues[1].payload, "Zwei\nZeilen");
+        assert_eq!(WebVtt::from_str(&result.to_string()).unwrap(), result);
+    }
+
+    #[test]
+    fn test_unit_keys_are_unique() {
+        let vtt = WebVtt::from_str(
+            "WEBVTT
+
+00:00:01.000 --> 00:00:02.000
+Hello
+
+2
+00:00:03.000 --> 00:00:04.000
+World
+
+#3
+00:00:05.000 --> 00:00:06.000
+Third
+
+00:00:07.000 --> 00:00:08.000
+Fourth",
+        )
+        .unwrap();
+        let keys: Vec<String> = vtt.unit_keys().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["#1", "2", "#3", "#4"]);
+
+        let mut vtt = vtt;
+        vtt.cues[3].identifier = Some("#3".to_string());
+        let keys: Vec<String> = vtt.unit_keys().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["#1", "2", "#3#3", "#3#4"]);
+
+        let po = vtt
+            .to_po("de")
+            .replace(
+                "msgid \"World\"\nmsgstr \"\"",
+                "msgid \"World\"\nmsgstr \"Welt\"",
+            )
+            .replace(
+                "msgid \"Third\"\nmsgstr \"\"",
+                "msgid \"Third\"\nmsgstr \"Dritte\"",
+            );
+        let result = vtt.apply_po(&po).unwrap();
+        let payloads: Vec<&str> = result.cues.iter().map(|c| c.payload.as_str()).collect();
+        assert_eq!(payloads, vec!["Hello", "Welt", "Dritte", "Fourth"]);
+        assert_eq!(vtt.to_xliff("en", "de").matches("<unit id=\"#3").count(), 2);
+    }
+
+    #[test]
+    fn test_xliff_cdata_comments_and_attributes() {
+        let vtt = original();
+        let xliff = r##"<?xml version="1.0"?>
+<xliff xmlns="urn:oasis:names:tc:xliff:document:2.0" version="2.0" srcLang="en" trgLang="de">
+  <!-- <unit id="intro"><segment><target>commented out</target></segment></unit> -->
+  <file id="f1">
+    <unit name="a > b" id='intro'>
+      <segment><target><![CDATA[<i>Hallo</i> & </target>]]></target></segment>
+    </unit>
+    <unit id="#2">
+      <segment><source>Two</source><target xml:lang="de">Zwei &amp;</target></segment>
+      <segment><target>
+Zeilen</target></segment>
+    </unit>
+  </file>
+</xliff>"##;
+        let result = vtt.apply_xliff(xliff).unwrap();
+        assert_eq!(result.cues[0].payload, "<i>Hallo</i> & </target>");
+        assert_eq!(result.cues[1].payload, "Zwei &\nZeilen");
+
+        for unsupported in [
+            r#"<xliff><file><unit id="intro"><segment><target>a <pc id="1">b</pc></target></segment></unit></file></xliff>"#,
+            r#"<!DOCTYPE xliff [<!ENTITY x "y">]><xliff/>"#,
+            r#"<xliff><file><unit id="intro"><segment><target>open"#,
+        ] {
+            assert!(matches!(
+                vtt.apply_xliff(unsupported),
+                Err(VttParseError::InvalidTranslation(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! End-to-end tests of the `vtt` command-line tool.
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+const INPUT: &str = "WEBVTT
+
+1
+00:00:01.000 --> 00:00:02.000
+<v Ann>Hello there
+
+2
+00:00:03.000 --> 00:00:04.000
+<v Bob>General Kenobi
+";
+
+/// The output of a run of the tool.
+struct Output {
+    code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+/// Runs the tool with the given arguments, writing `stdin` to its input.
+fn vtt(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vtt"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The tool may exit before reading its input, e.g. on usage errors.
+    let written = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    if let Err(e) = written {
+        assert_eq!(e.kind(), ErrorKind::BrokenPipe);
+    }
+    let output = child.wait_with_output().unwrap();
+    Output {
+        code: output.status.code().unwrap(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+/// Writes a file to a directory of its own for the test `name`.
+fn temp_file(name: &str, file: &str, contents: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(file);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_validate() {
+    let output = vtt(&["validate"], INPUT);
+    assert_eq!(output.code, 0);
+    assert_eq!(output.stdout, "-: valid, 2 cues\n");
+
+    let output = vtt(&["validate", "--json", "-"], "not a caption file");
+    assert_eq!(output.code, 1);
+    let json: serde_json::Value = serde_json::from_str(&output.stdout).unwrap();
+    assert_eq!(json["valid"], false);
+}
+
+#[test]
+fn test_fmt() {
+    let output = vtt(&["fmt"], "WEBVTT\n\n00:01.000 --> 00:02.000\nHi\n");
+    assert_eq!(output.code, 0);
+    assert_eq!(
+        output.stdout,
+        "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHi\n"
+    );
+
+    let output = vtt(&["fmt", "--line-ending", "crlf"], INPUT);
+    assert_eq!(output.code, 0);
+    assert_eq!(output.stdout, INPUT.replace('\n', "\r\n"));
+}
+
+#[test]
+fn test_convert() {
+    let output = vtt(&["convert", "--to", "json"], INPUT);
+    assert_eq!(output.code, 0);
+    let json: serde_json::Value = serde_json::from_str(&output.stdout).unwrap();
+    assert_eq!(json["cues"][0]["id"], "1");
+    assert_eq!(json["cues"][0]["start"], 1000);
+    assert_eq!(json["cues"][1]["payload"], "<v Bob>General Kenobi");
+
+    let output = vtt(&["convert", "--to=txt"], INPUT);
+    assert_eq!(output.stdout, "Hello there\n\nGeneral Kenobi\n");
+
+    let output = vtt(&["convert", "--to", "po", "--target-lang", "de"], INPUT);
+    assert!(output.stdout.contains("\"Language: de\\n\""));
+    assert!(output.stdout.contains("msgid \"<v Ann>Hello there\""));
+
+    let output = vtt(&["convert", "--to", "xliff"], INPUT);
+    assert!(output.stdout.contains("srcLang=\"en\""));
+
+    let output = vtt(&["convert", "--to", "srt"], INPUT);
+    assert_eq!(output.code, 2);
+    assert!(output.stderr.starts_with("vtt: unknown format: srt"));
+}
+
+#[test]
+fn test_shift() {
+    let output = vtt(&["shift", "--by", "-500ms"], INPUT);
+    assert_eq!(output.code, 0);
+    assert!(output
+        .stdout
+        .contains("00:00:00.500 --> 00:00:01.500\n<v Ann>Hello there"));
+
+    let output = vtt(&["shift", "--by", "soon"], INPUT);
+    assert_eq!(output.code, 2);
+}
+
+#[test]
+fn test_lint() {
+    let output = vtt(&["lint", "--json"], INPUT);
+    assert_eq!(output.code, 0);
+    assert_eq!(output.stdout.trim(), "[]");
+
+    let output = vtt(&["lint", "--max-cps", "1"], INPUT);
+    assert_eq!(output.code, 1);
+    assert!(output.stdout.contains("characters per second"));
+}
+
+#[test]
+fn test_stats() {
+    let output = vtt(&["stats"], INPUT);
+    assert_eq!(output.code, 0);
+    assert!(output.stdout.starts_with("cues: 2\n"));
+    assert!(output
+        .stdout
+        .contains("speaker Ann: 1 cues, 00:00:01.000\n"));
+}
+
+#[test]
+fn test_merge() {
+    let base = temp_file("merge", "base.vtt", INPUT);
+    let ours = temp_file(
+        "merge",
+        "ours.vtt",
+        &INPUT.replace("Hello there", "Hello there!"),
+    );
+    let theirs = temp_file(
+        "merge",
+        "theirs.vtt",
+        &INPUT.replace("General Kenobi", "General Kenobi!"),
+    );
+    let merged = base.with_file_name("merged.vtt");
+    let paths = [&base, &ours, &theirs, &merged].map(|path| path.to_str().unwrap());
+
+    let output = vtt(&["merge", paths[0], paths[1], paths[2], "-o", paths[3]], "");
+    assert_eq!(output.code, 0);
+    assert_eq!(output.stdout, "");
+    assert_eq!(
+        fs::read_to_string(&merged).unwrap(),
+        INPUT
+            .replace("Hello there", "Hello there!")
+            .replace("General Kenobi", "General Kenobi!")
+    );
+
+    let output = vtt(&["merge", paths[0], paths[1]], "");
+    assert_eq!(output.code, 2);
+}
+
+#[test]
+fn test_usage_errors() {
+    for args in [
+        &[][..],
+        &["transmogrify"],
+        &["fmt", "--bogus", "in.vtt"],
+        &["fmt", "-x"],
+        &["fmt", "--line-ending", "lfcr"],
+        &["shift"],
+        &["fmt", "a.vtt", "b.vtt"],
+    ] {
+        let output = vtt(args, INPUT);
+        assert_eq!(output.code, 2, "{:?}", args);
+        assert!(output.stderr.contains("Usage: vtt"), "{:?}", args);
+        assert_eq!(output.stdout, "", "{:?}", args);
+    }
+
+    let output = vtt(&["--help"], "");
+    assert_eq!(output.code, 0);
+    assert!(output.stdout.starts_with("Usage: vtt"));
+}
+
+#[test]
+fn test_io_errors() {
+    let output = vtt(&["validate", "does-not-exist.vtt"], "");
+    assert_eq!(output.code, 3);
+    assert!(output.stderr.starts_with("vtt: does-not-exist.vtt: "));
+}